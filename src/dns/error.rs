@@ -0,0 +1,161 @@
+use super::MAX_PACKET_SIZE;
+use alloc::string::String;
+use core::fmt;
+
+/// Error returned when a DNS message can't be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// The message ended before the end of a field.
+    UnexpectedEof,
+    /// A label uses the reserved `01` or `10` prefix.
+    InvalidLabel,
+    /// A compression pointer doesn't point to a previous label.
+    InvalidPointer,
+    /// A name is longer than 255 bytes once decompressed.
+    NameTooLong,
+    /// A name contains a label that is not valid UTF-8.
+    InvalidName,
+    /// The type of a question is not known.
+    InvalidQueryType(u16),
+    /// The class of a question is not known.
+    InvalidQueryClass(u16),
+    /// The class of a record is not known.
+    InvalidClass(u16),
+    /// The rdata of a record doesn't match its type.
+    InvalidRData,
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::UnexpectedEof => write!(f, "unexpected end of message"),
+            DecodeError::InvalidLabel => write!(f, "invalid label type"),
+            DecodeError::InvalidPointer => write!(f, "invalid name compression pointer"),
+            DecodeError::NameTooLong => write!(f, "name is longer than 255 bytes"),
+            DecodeError::InvalidName => write!(f, "name is not valid UTF-8"),
+            DecodeError::InvalidQueryType(code) => write!(f, "invalid query type: {}", code),
+            DecodeError::InvalidQueryClass(code) => write!(f, "invalid query class: {}", code),
+            DecodeError::InvalidClass(code) => write!(f, "invalid class: {}", code),
+            DecodeError::InvalidRData => write!(f, "invalid record data"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DecodeError {}
+
+/// Error returned when a DNS message can't be encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    /// A name contains an empty label, for example `foo..local`.
+    EmptyLabel,
+    /// A name contains a label longer than 63 bytes.
+    LabelTooLong,
+    /// A name is longer than 255 bytes once encoded.
+    NameTooLong,
+    /// A TXT string is longer than 255 bytes.
+    StringTooLong,
+    /// The rdata of a record is longer than 65535 bytes.
+    RDataTooLong,
+    /// The Wake-on-LAN password of an EDNS0 Owner option is not 4 or 6 bytes long.
+    InvalidPassword,
+    /// The packet is longer than the maximum mDNS packet size, once the IP and UDP headers are
+    /// accounted for.
+    PacketTooLong(usize),
+    /// The buffer supplied by the caller is smaller than the packet, of the given length.
+    BufferTooSmall(usize),
+}
+
+impl fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EncodeError::EmptyLabel => write!(f, "name contains an empty label"),
+            EncodeError::LabelTooLong => write!(f, "name contains a label longer than 63 bytes"),
+            EncodeError::NameTooLong => write!(f, "name is longer than 255 bytes"),
+            EncodeError::StringTooLong => write!(f, "TXT string is longer than 255 bytes"),
+            EncodeError::RDataTooLong => write!(f, "record data is longer than 65535 bytes"),
+            EncodeError::InvalidPassword => {
+                write!(f, "owner option password is not 4 or 6 bytes long")
+            }
+            EncodeError::PacketTooLong(len) => {
+                write!(
+                    f,
+                    "packet is {} bytes long, which is more than {} bytes",
+                    len, MAX_PACKET_SIZE
+                )
+            }
+            EncodeError::BufferTooSmall(len) => {
+                write!(f, "buffer is too small for a packet of {} bytes", len)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for EncodeError {}
+
+/// Error returned when a record can't be parsed from its presentation format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The line ended before the given field.
+    MissingField(&'static str),
+    /// A quoted string is not terminated.
+    UnterminatedString,
+    /// The ttl is not a 32 bits integer.
+    InvalidTtl(String),
+    /// The class is not known.
+    InvalidClass(String),
+    /// The type is not known.
+    InvalidType(String),
+    /// The rdata doesn't match the type of the record.
+    InvalidRData(String),
+    /// The octets of a label of a name are not valid UTF-8.
+    InvalidName(String),
+    /// A master file contains a directive other than `$ORIGIN` and `$TTL`, or a directive with
+    /// invalid arguments.
+    InvalidDirective(String),
+    /// A parenthesis of a master file is not closed, or closes no parenthesis.
+    UnbalancedParentheses,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::MissingField(field) => write!(f, "missing {}", field),
+            ParseError::UnterminatedString => write!(f, "unterminated quoted string"),
+            ParseError::InvalidTtl(ttl) => write!(f, "invalid ttl: {}", ttl),
+            ParseError::InvalidClass(class) => write!(f, "invalid class: {}", class),
+            ParseError::InvalidType(rtype) => write!(f, "invalid type: {}", rtype),
+            ParseError::InvalidRData(rdata) => write!(f, "invalid record data: {}", rdata),
+            ParseError::InvalidName(name) => write!(f, "invalid name: {}", name),
+            ParseError::InvalidDirective(directive) => {
+                write!(f, "invalid directive: {}", directive)
+            }
+            ParseError::UnbalancedParentheses => write!(f, "unbalanced parentheses"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+/// Error returned when a master file can't be parsed, with the line where parsing failed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ZoneError {
+    /// The line of the error, starting at 1.
+    pub line: usize,
+    pub error: ParseError,
+}
+
+impl fmt::Display for ZoneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.error)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ZoneError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
@@ -1,17 +1,55 @@
+use core::convert::TryInto;
+use core::fmt;
+
 use super::append_u16;
+use super::error::{DecodeError, EncodeError};
+use super::reader::{ReadBytes, Reader};
 use super::traits::AppendBytes;
+use super::writer::Writer;
 
-#[derive(Default)]
+/// The header of a DNS message (RFC 1035 §4.1.1).
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(
+        from = "crate::dns::serialization::Header",
+        into = "crate::dns::serialization::Header"
+    )
+)]
 pub struct PacketHeader {
     id: u16,
     flags: u16,
     pub(crate) qd_count: u16,
     pub(crate) an_count: u16,
-    ns_count: u16,
-    ar_count: u16,
+    pub(crate) ns_count: u16,
+    pub(crate) ar_count: u16,
 }
 
+const QR: u16 = 1 << 15;
+const AA: u16 = 1 << 10;
+const TC: u16 = 1 << 9;
+const RD: u16 = 1 << 8;
+const RA: u16 = 1 << 7;
+const AD: u16 = 1 << 5;
+const CD: u16 = 1 << 4;
+const OPCODE_MASK: u16 = 0x7800;
+const RCODE_MASK: u16 = 0x000f;
+
 impl PacketHeader {
+    /// Parses a header from its wire format.
+    pub fn parse(buf: &[u8; 12]) -> Self {
+        let field = |idx: usize| u16::from_be_bytes([buf[idx], buf[idx + 1]]);
+        Self {
+            id: field(0),
+            flags: field(2),
+            qd_count: field(4),
+            an_count: field(6),
+            ns_count: field(8),
+            ar_count: field(10),
+        }
+    }
+
     pub fn set_id(&mut self, id: u16) -> &mut Self {
         self.id = id;
         self
@@ -24,99 +62,456 @@ impl PacketHeader {
     /// Set the `QR` bit. Specifies whether the message is a query (true) or a response (false).
     /// Defaults to true.
     pub fn set_query(&mut self, set: bool) -> &mut Self {
-        self.flags = ((-(!set as i16) ^ self.flags as i16) & (1 << 15)) as u16;
+        self.set_flag(QR, !set);
         self
     }
 
+    /// Returns whether the message is a query, that is whether the `QR` bit is cleared.
     pub fn is_query(&self) -> bool {
-        self.flags & 1 << 15 != 0
+        self.flags & QR == 0
     }
 
+    /// Sets the kind of query. Defaults to `OpCode::QUERY`.
     pub fn set_opcode(&mut self, code: OpCode) -> &mut Self {
-        // clear previous value
-        self.flags &= 0x87ff;
-        // set new value
-        self.flags |= (code as u16) << 11;
+        self.flags = self.flags & !OPCODE_MASK | (u16::from(code) << 11) & OPCODE_MASK;
         self
     }
 
+    pub fn opcode(&self) -> OpCode {
+        OpCode::from((self.flags & OPCODE_MASK) >> 11)
+    }
+
+    /// Set the `AA` bit. In mDNS, every response is authoritative (RFC 6762 §18.4).
     pub fn set_aa(&mut self, set: bool) -> &mut Self {
-        self.flags = ((-(set as i16) ^ self.flags as i16) & (1 << 10)) as u16;
+        self.set_flag(AA, set);
         self
     }
 
     pub fn aa(&self) -> bool {
-        self.flags & 1 << 10 != 0
+        self.flags & AA != 0
     }
 
+    /// Set the `TC` bit. In mDNS queries, it indicates that more known answers follow in the next
+    /// packets (RFC 6762 §7.2).
     pub fn set_tc(&mut self, set: bool) -> &mut Self {
-        self.flags = ((-(set as i16) ^ self.flags as i16) & (1 << 9)) as u16;
+        self.set_flag(TC, set);
         self
     }
 
     pub fn tc(&self) -> bool {
-        self.flags & 1 << 9 != 0
+        self.flags & TC != 0
     }
 
+    /// Set the `RD` bit, which asks for recursion.
     pub fn set_rd(&mut self, set: bool) -> &mut Self {
-        self.flags = ((-(set as i16) ^ self.flags as i16) & (1 << 8)) as u16;
+        self.set_flag(RD, set);
         self
     }
 
     pub fn rd(&self) -> bool {
-        self.flags & 1 << 8 != 0
+        self.flags & RD != 0
     }
 
+    /// Set the `RA` bit, which tells that recursion is available.
     pub fn set_ra(&mut self, set: bool) -> &mut Self {
-        self.flags = ((-(set as i16) ^ self.flags as i16) & (1 << 7)) as u16;
+        self.set_flag(RA, set);
         self
     }
 
     pub fn ra(&self) -> bool {
-        self.flags & 1 << 7 != 0
+        self.flags & RA != 0
+    }
+
+    /// Set the `AD` bit, which tells that the data is authenticated (RFC 4035 §3.2.3).
+    pub fn set_ad(&mut self, set: bool) -> &mut Self {
+        self.set_flag(AD, set);
+        self
     }
 
+    pub fn ad(&self) -> bool {
+        self.flags & AD != 0
+    }
+
+    /// Set the `CD` bit, which disables DNSSEC checking (RFC 4035 §3.2.2).
+    pub fn set_cd(&mut self, set: bool) -> &mut Self {
+        self.set_flag(CD, set);
+        self
+    }
+
+    pub fn cd(&self) -> bool {
+        self.flags & CD != 0
+    }
+
+    fn set_flag(&mut self, mask: u16, set: bool) {
+        if set {
+            self.flags |= mask;
+        } else {
+            self.flags &= !mask;
+        }
+    }
+
+    /// Sets the response code. The header only holds the lower 4 bits of the code: the upper bits
+    /// of extended codes are carried by the OPT record (RFC 6891 §6.1.3).
     pub fn set_rcode(&mut self, code: RCode) -> &mut Self {
-        // clear previous value
-        self.flags &= 0xfff8;
-        // set new value
-        self.flags |= code as u16;
+        self.flags = self.flags & !RCODE_MASK | u16::from(code) & RCODE_MASK;
         self
     }
 
-    pub fn an_count(self) -> u16 {
+    /// Returns the response code held by the header, that is the lower 4 bits of the code.
+    pub fn rcode(&self) -> RCode {
+        RCode::from(self.flags & RCODE_MASK)
+    }
+
+    pub fn set_qd_count(&mut self, count: u16) -> &mut Self {
+        self.qd_count = count;
+        self
+    }
+    pub fn qd_count(&self) -> u16 {
+        self.qd_count
+    }
+    pub fn set_an_count(&mut self, count: u16) -> &mut Self {
+        self.an_count = count;
+        self
+    }
+    pub fn an_count(&self) -> u16 {
         self.an_count
     }
-    pub fn ns_count(self) -> u16 {
+    pub fn set_ns_count(&mut self, count: u16) -> &mut Self {
+        self.ns_count = count;
+        self
+    }
+    pub fn ns_count(&self) -> u16 {
         self.ns_count
     }
-    pub fn ar_count(self) -> u16 {
+    pub fn set_ar_count(&mut self, count: u16) -> &mut Self {
+        self.ar_count = count;
+        self
+    }
+    pub fn ar_count(&self) -> u16 {
         self.ar_count
     }
 }
 
 impl AppendBytes for PacketHeader {
-    fn append_bytes(&self, out: &mut Vec<u8>) {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
         append_u16(out, self.id);
         append_u16(out, self.flags);
         append_u16(out, self.qd_count);
         append_u16(out, self.an_count);
         append_u16(out, self.ns_count);
         append_u16(out, self.ar_count);
+        Ok(())
     }
 }
 
+impl ReadBytes<'_> for PacketHeader {
+    fn read_bytes(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let buf = reader.read_slice(12)?;
+        Ok(Self::parse(buf.try_into().expect("a slice of 12 bytes")))
+    }
+}
+
+impl fmt::Display for PacketHeader {
+    /// Writes the header as printed by `dig`, on two lines.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, ";; ->>HEADER<<- opcode: ")?;
+        match self.opcode() {
+            OpCode::Unknown(code) => write!(f, "RESERVED{}", code)?,
+            opcode => write!(f, "{:?}", opcode)?,
+        }
+        write!(f, ", status: ")?;
+        match self.rcode() {
+            RCode::NoError => write!(f, "NOERROR")?,
+            RCode::FormatError => write!(f, "FORMERR")?,
+            RCode::ServerFailure => write!(f, "SERVFAIL")?,
+            RCode::NameError => write!(f, "NXDOMAIN")?,
+            RCode::NotImplemented => write!(f, "NOTIMP")?,
+            RCode::Refused => write!(f, "REFUSED")?,
+            RCode::YXDomain => write!(f, "YXDOMAIN")?,
+            RCode::YXRRSet => write!(f, "YXRRSET")?,
+            RCode::NXRRSet => write!(f, "NXRRSET")?,
+            RCode::NotAuth => write!(f, "NOTAUTH")?,
+            RCode::NotZone => write!(f, "NOTZONE")?,
+            RCode::DSOTypeNotImplemented => write!(f, "DSOTYPENI")?,
+            code => write!(f, "RCODE{}", u16::from(code))?,
+        }
+        writeln!(f, ", id: {}", self.id)?;
+
+        write!(f, ";; flags:")?;
+        let flags = [
+            (QR, "qr"),
+            (AA, "aa"),
+            (TC, "tc"),
+            (RD, "rd"),
+            (RA, "ra"),
+            (AD, "ad"),
+            (CD, "cd"),
+        ];
+        for (mask, name) in flags.iter() {
+            if self.flags & mask != 0 {
+                write!(f, " {}", name)?;
+            }
+        }
+        write!(
+            f,
+            "; QUERY: {}, ANSWER: {}, AUTHORITY: {}, ADDITIONAL: {}",
+            self.qd_count, self.an_count, self.ns_count, self.ar_count
+        )
+    }
+}
+
+/// The kind of query of a message (RFC 6895 §2.2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum OpCode {
-    QUERY = 0x0,
-    IQUERY = 0x2,
-    STATUS = 0x3,
+    QUERY,
+    /// Inverse query, obsoleted by RFC 3425.
+    IQUERY,
+    STATUS,
+    NOTIFY,
+    UPDATE,
+    /// DNS Stateful Operations (RFC 8490).
+    DSO,
+    /// An unassigned opcode.
+    Unknown(u16),
+}
+
+impl From<u16> for OpCode {
+    fn from(code: u16) -> Self {
+        match code {
+            0 => OpCode::QUERY,
+            1 => OpCode::IQUERY,
+            2 => OpCode::STATUS,
+            4 => OpCode::NOTIFY,
+            5 => OpCode::UPDATE,
+            6 => OpCode::DSO,
+            code => OpCode::Unknown(code),
+        }
+    }
+}
+
+impl From<OpCode> for u16 {
+    fn from(code: OpCode) -> Self {
+        match code {
+            OpCode::QUERY => 0,
+            OpCode::IQUERY => 1,
+            OpCode::STATUS => 2,
+            OpCode::NOTIFY => 4,
+            OpCode::UPDATE => 5,
+            OpCode::DSO => 6,
+            OpCode::Unknown(code) => code,
+        }
+    }
 }
 
+/// The response code of a message (RFC 6895 §2.3). Codes above 15 only fit in a message with an
+/// OPT record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RCode {
-    NoError = 0,
-    FormatError = 1,
-    ServerFailure = 2,
-    NameError = 3,
-    NotImplemmented = 4,
-    Refused = 5,
+    NoError,
+    FormatError,
+    ServerFailure,
+    NameError,
+    NotImplemented,
+    Refused,
+    YXDomain,
+    YXRRSet,
+    NXRRSet,
+    NotAuth,
+    NotZone,
+    DSOTypeNotImplemented,
+    BadVersion,
+    BadKey,
+    BadTime,
+    BadMode,
+    BadName,
+    BadAlgorithm,
+    BadTruncation,
+    BadCookie,
+    /// An unassigned response code.
+    Unknown(u16),
+}
+
+impl From<u16> for RCode {
+    fn from(code: u16) -> Self {
+        match code {
+            0 => RCode::NoError,
+            1 => RCode::FormatError,
+            2 => RCode::ServerFailure,
+            3 => RCode::NameError,
+            4 => RCode::NotImplemented,
+            5 => RCode::Refused,
+            6 => RCode::YXDomain,
+            7 => RCode::YXRRSet,
+            8 => RCode::NXRRSet,
+            9 => RCode::NotAuth,
+            10 => RCode::NotZone,
+            11 => RCode::DSOTypeNotImplemented,
+            16 => RCode::BadVersion,
+            17 => RCode::BadKey,
+            18 => RCode::BadTime,
+            19 => RCode::BadMode,
+            20 => RCode::BadName,
+            21 => RCode::BadAlgorithm,
+            22 => RCode::BadTruncation,
+            23 => RCode::BadCookie,
+            code => RCode::Unknown(code),
+        }
+    }
+}
+
+impl From<RCode> for u16 {
+    fn from(code: RCode) -> Self {
+        match code {
+            RCode::NoError => 0,
+            RCode::FormatError => 1,
+            RCode::ServerFailure => 2,
+            RCode::NameError => 3,
+            RCode::NotImplemented => 4,
+            RCode::Refused => 5,
+            RCode::YXDomain => 6,
+            RCode::YXRRSet => 7,
+            RCode::NXRRSet => 8,
+            RCode::NotAuth => 9,
+            RCode::NotZone => 10,
+            RCode::DSOTypeNotImplemented => 11,
+            RCode::BadVersion => 16,
+            RCode::BadKey => 17,
+            RCode::BadTime => 18,
+            RCode::BadMode => 19,
+            RCode::BadName => 20,
+            RCode::BadAlgorithm => 21,
+            RCode::BadTruncation => 22,
+            RCode::BadCookie => 23,
+            RCode::Unknown(code) => code,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::writer::Writer;
+    use super::*;
+    use proptest::prelude::*;
+
+    fn encode(header: &PacketHeader) -> [u8; 12] {
+        let mut buf = [0; 12];
+        header
+            .append_bytes(&mut Writer::new(&mut buf, false))
+            .unwrap();
+        buf
+    }
+
+    #[test]
+    fn default_header_is_query() {
+        let header = PacketHeader::default();
+        assert!(header.is_query());
+        assert_eq!(header.opcode(), OpCode::QUERY);
+        assert_eq!(header.rcode(), RCode::NoError);
+    }
+
+    #[test]
+    fn setters_keep_other_flags() {
+        let mut header = PacketHeader::default();
+        header
+            .set_query(false)
+            .set_aa(true)
+            .set_rd(true)
+            .set_opcode(OpCode::UPDATE)
+            .set_rcode(RCode::Refused);
+        assert!(!header.is_query());
+        assert!(header.aa());
+        assert!(header.rd());
+        assert!(!header.tc());
+        assert_eq!(header.opcode(), OpCode::UPDATE);
+        assert_eq!(header.rcode(), RCode::Refused);
+        assert_eq!(encode(&header)[2..4], [0xad, 0x05]);
+
+        header.set_query(true).set_aa(false);
+        assert!(header.is_query());
+        assert!(!header.aa());
+        assert!(header.rd());
+        assert_eq!(header.opcode(), OpCode::UPDATE);
+    }
+
+    #[test]
+    fn display_header() {
+        let mut header = PacketHeader::default();
+        header
+            .set_id(7)
+            .set_query(false)
+            .set_aa(true)
+            .set_cd(true)
+            .set_opcode(OpCode::UPDATE)
+            .set_rcode(RCode::NotZone);
+        assert_eq!(
+            header.to_string(),
+            ";; ->>HEADER<<- opcode: UPDATE, status: NOTZONE, id: 7
+;; flags: qr aa cd; QUERY: 0, ANSWER: 0, AUTHORITY: 0, ADDITIONAL: 0"
+        );
+
+        header
+            .set_opcode(OpCode::Unknown(9))
+            .set_rcode(RCode::from(13));
+        assert!(header
+            .to_string()
+            .starts_with(";; ->>HEADER<<- opcode: RESERVED9, status: RCODE13, id: 7\n"));
+    }
+
+    proptest! {
+        #[test]
+        fn parse_round_trip(buf in any::<[u8; 12]>()) {
+            let header = PacketHeader::parse(&buf);
+            prop_assert_eq!(encode(&header), buf);
+            let mut reader = Reader::new(&buf);
+            prop_assert_eq!(PacketHeader::read_bytes(&mut reader).unwrap(), header);
+        }
+
+        #[test]
+        fn setters_round_trip(
+            id in any::<u16>(),
+            flags in any::<[bool; 7]>(),
+            opcode in 0u16..16,
+            rcode in 0u16..16,
+            counts in any::<[u16; 4]>(),
+        ) {
+            let mut header = PacketHeader::default();
+            header
+                .set_id(id)
+                .set_query(flags[0])
+                .set_aa(flags[1])
+                .set_tc(flags[2])
+                .set_rd(flags[3])
+                .set_ra(flags[4])
+                .set_ad(flags[5])
+                .set_cd(flags[6])
+                .set_opcode(OpCode::from(opcode))
+                .set_rcode(RCode::from(rcode))
+                .set_qd_count(counts[0])
+                .set_an_count(counts[1])
+                .set_ns_count(counts[2])
+                .set_ar_count(counts[3]);
+
+            let parsed = PacketHeader::parse(&encode(&header));
+            prop_assert_eq!(&parsed, &header);
+            prop_assert_eq!(parsed.id(), id);
+            prop_assert_eq!(
+                [parsed.is_query(), parsed.aa(), parsed.tc(), parsed.rd(), parsed.ra(), parsed.ad(), parsed.cd()],
+                flags
+            );
+            prop_assert_eq!(u16::from(parsed.opcode()), opcode);
+            prop_assert_eq!(u16::from(parsed.rcode()), rcode);
+            prop_assert_eq!(
+                [parsed.qd_count(), parsed.an_count(), parsed.ns_count(), parsed.ar_count()],
+                counts
+            );
+        }
+
+        #[test]
+        fn code_conversions_round_trip(code in any::<u16>()) {
+            prop_assert_eq!(u16::from(OpCode::from(code)), code);
+            prop_assert_eq!(u16::from(RCode::from(code)), code);
+        }
+    }
 }
@@ -0,0 +1,493 @@
+use super::error::DecodeError;
+use super::header::{OpCode, PacketHeader, RCode};
+use super::packet::Question;
+use super::rdata::opt::Record as Opt;
+use super::reader::{ReadBytes, Reader};
+use super::update::{Prerequisite, Prerequisites, Update, Updates};
+use super::{RData, ResourceRecord};
+use alloc::vec::Vec;
+
+/// A decoded DNS message.
+///
+/// Records of a type that is not supported by `RData` are kept as `RData::Unknown`, and records of
+/// a class that is not supported by `Class` are kept with `Class::Unknown`.
+///
+/// In an UPDATE message, the answer and authority sections are decoded as prerequisites and
+/// updates, and `answers` and `authorities` are empty.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Message {
+    header: PacketHeader,
+    questions: Vec<Question<'static>>,
+    answers: Vec<ResourceRecord<'static>>,
+    authorities: Vec<ResourceRecord<'static>>,
+    additionals: Vec<ResourceRecord<'static>>,
+    prerequisites: Vec<Prerequisite<'static>>,
+    updates: Vec<Update<'static>>,
+}
+
+impl Message {
+    /// Decodes a DNS message from its wire format.
+    pub fn parse(buf: &[u8]) -> Result<Self, DecodeError> {
+        MessageRef::parse(buf)?.into_owned()
+    }
+
+    /// Returns the header of the message.
+    pub fn header(&self) -> &PacketHeader {
+        &self.header
+    }
+
+    /// Returns the questions of the message.
+    pub fn questions(&self) -> &[Question<'static>] {
+        &self.questions
+    }
+
+    /// Returns the records of the answer section.
+    pub fn answers(&self) -> &[ResourceRecord<'static>] {
+        &self.answers
+    }
+
+    /// Returns the records of the authority section.
+    pub fn authorities(&self) -> &[ResourceRecord<'static>] {
+        &self.authorities
+    }
+
+    /// Returns the records of the additional section.
+    pub fn additionals(&self) -> &[ResourceRecord<'static>] {
+        &self.additionals
+    }
+
+    /// Returns the zone of an UPDATE message, which is its only question.
+    pub fn zone(&self) -> Option<&Question<'static>> {
+        self.questions.first()
+    }
+
+    /// Returns the prerequisites of an UPDATE message.
+    pub fn prerequisites(&self) -> &[Prerequisite<'static>] {
+        &self.prerequisites
+    }
+
+    /// Returns the updates of an UPDATE message.
+    pub fn updates(&self) -> &[Update<'static>] {
+        &self.updates
+    }
+
+    /// Returns the response code of the message, including its upper bits carried by the OPT
+    /// record, if any.
+    pub fn rcode(&self) -> RCode {
+        let extended = self.opt().map_or(0, |opt| opt.extended_rcode as u16);
+        RCode::from(extended << 4 | u16::from(self.header.rcode()))
+    }
+
+    /// Returns the EDNS0 OPT pseudo-record of the additional section, if any.
+    pub fn opt(&self) -> Option<&Opt<'static>> {
+        self.additionals
+            .iter()
+            .find_map(|record| match record.data() {
+                RData::OPT(opt) => Some(opt),
+                _ => None,
+            })
+    }
+}
+
+fn collect_owned(records: Records) -> Result<Vec<ResourceRecord<'static>>, DecodeError> {
+    records
+        .map(|record| record.map(ResourceRecord::into_owned))
+        .collect()
+}
+
+/// A DNS message decoded without allocating, whose questions and records borrow the message.
+///
+/// Parsing only checks the framing of the message: every name is checked, and the rdata of every
+/// record must fit in the message. The questions and records themselves are decoded when the
+/// sections are iterated, and names are only decompressed when their labels are read.
+#[derive(Clone)]
+pub struct MessageRef<'a> {
+    header: PacketHeader,
+    /// Readers positioned at the start of the question, answer, authority and additional sections.
+    sections: [Reader<'a>; 4],
+}
+
+impl<'a> MessageRef<'a> {
+    /// Checks the framing of a DNS message in its wire format.
+    pub fn parse(buf: &'a [u8]) -> Result<Self, DecodeError> {
+        let mut reader = Reader::new(buf);
+        let header = PacketHeader::read_bytes(&mut reader)?;
+
+        let questions = reader.clone();
+        for _ in 0..header.qd_count {
+            reader.read_name()?;
+            reader.read_slice(4)?;
+        }
+        let answers = reader.clone();
+        skip_records(&mut reader, header.an_count)?;
+        let authorities = reader.clone();
+        skip_records(&mut reader, header.ns_count)?;
+        let additionals = reader.clone();
+        skip_records(&mut reader, header.ar_count)?;
+
+        Ok(Self {
+            header,
+            sections: [questions, answers, authorities, additionals],
+        })
+    }
+
+    /// Returns the header of the message.
+    pub fn header(&self) -> &PacketHeader {
+        &self.header
+    }
+
+    /// Returns an iterator over the questions of the message.
+    pub fn questions(&self) -> Questions<'a> {
+        Questions {
+            reader: self.sections[0].clone(),
+            remaining: self.header.qd_count,
+        }
+    }
+
+    /// Returns an iterator over the records of the answer section.
+    pub fn answers(&self) -> Records<'a> {
+        self.records(1, self.header.an_count)
+    }
+
+    /// Returns an iterator over the records of the authority section.
+    pub fn authorities(&self) -> Records<'a> {
+        self.records(2, self.header.ns_count)
+    }
+
+    /// Returns an iterator over the records of the additional section.
+    pub fn additionals(&self) -> Records<'a> {
+        self.records(3, self.header.ar_count)
+    }
+
+    /// Returns an iterator over the answer section of an UPDATE message, decoded as
+    /// prerequisites.
+    pub fn prerequisites(&self) -> Prerequisites<'a> {
+        Prerequisites {
+            reader: self.sections[1].clone(),
+            remaining: self.header.an_count,
+        }
+    }
+
+    /// Returns an iterator over the authority section of an UPDATE message, decoded as updates.
+    pub fn updates(&self) -> Updates<'a> {
+        Updates {
+            reader: self.sections[2].clone(),
+            remaining: self.header.ns_count,
+        }
+    }
+
+    /// Decodes the questions and records of the message, and converts them into a `Message` that
+    /// owns its data.
+    pub fn into_owned(self) -> Result<Message, DecodeError> {
+        let is_update = self.header.opcode() == OpCode::UPDATE;
+        Ok(Message {
+            header: self.header.clone(),
+            questions: self
+                .questions()
+                .map(|question| question.map(Question::into_owned))
+                .collect::<Result<_, _>>()?,
+            answers: if is_update {
+                Vec::new()
+            } else {
+                collect_owned(self.answers())?
+            },
+            authorities: if is_update {
+                Vec::new()
+            } else {
+                collect_owned(self.authorities())?
+            },
+            additionals: collect_owned(self.additionals())?,
+            prerequisites: if is_update {
+                self.prerequisites()
+                    .map(|prerequisite| prerequisite.map(Prerequisite::into_owned))
+                    .collect::<Result<_, _>>()?
+            } else {
+                Vec::new()
+            },
+            updates: if is_update {
+                self.updates()
+                    .map(|update| update.map(Update::into_owned))
+                    .collect::<Result<_, _>>()?
+            } else {
+                Vec::new()
+            },
+        })
+    }
+
+    fn records(&self, section: usize, count: u16) -> Records<'a> {
+        Records {
+            reader: self.sections[section].clone(),
+            remaining: count,
+        }
+    }
+}
+
+fn skip_records(reader: &mut Reader, count: u16) -> Result<(), DecodeError> {
+    for _ in 0..count {
+        reader.read_name()?;
+        // type, class and ttl
+        reader.read_slice(8)?;
+        let len = reader.read_u16()? as usize;
+        reader.read_slice(len)?;
+    }
+    Ok(())
+}
+
+/// Iterator over the questions of a `MessageRef`. Iteration stops after the first error.
+pub struct Questions<'a> {
+    reader: Reader<'a>,
+    remaining: u16,
+}
+
+impl<'a> Iterator for Questions<'a> {
+    type Item = Result<Question<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let question = Question::read_bytes(&mut self.reader);
+        if question.is_err() {
+            self.remaining = 0;
+        }
+        Some(question)
+    }
+}
+
+/// Iterator over the records of a section of a `MessageRef`. Iteration stops after the first
+/// error.
+pub struct Records<'a> {
+    reader: Reader<'a>,
+    remaining: u16,
+}
+
+impl<'a> Iterator for Records<'a> {
+    type Item = Result<ResourceRecord<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let record = ResourceRecord::read(&mut self.reader);
+        if record.is_err() {
+            self.remaining = 0;
+        }
+        Some(record)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dns::{PacketBuilder, QueryClass, QueryType};
+    use alloc::borrow::Cow;
+
+    #[test]
+    fn parse_query() {
+        let mut packet = PacketBuilder::new();
+        packet.header_mut().set_id(42);
+        packet.add_question(
+            false,
+            "_myservice._tcp.local",
+            QueryClass::IN,
+            QueryType::PTR,
+        );
+        let message = Message::parse(&packet.build()).unwrap();
+
+        assert_eq!(message.header().id(), 42);
+        assert_eq!(message.questions().len(), 1);
+        let question = &message.questions()[0];
+        assert_eq!(question.name, "_myservice._tcp.local");
+        assert_eq!(question.qtype, QueryType::PTR);
+        assert_eq!(question.qclass, QueryClass::IN);
+        assert!(!question.prefer_unicast);
+    }
+
+    #[test]
+    fn parse_unicast_query() {
+        let mut packet = PacketBuilder::new();
+        packet.add_question(
+            true,
+            "_myservice._tcp.local",
+            QueryClass::IN,
+            QueryType::PTR,
+        );
+        let packet = packet.build();
+        // the QU bit is the top bit of the class
+        assert_eq!(&packet[packet.len() - 2..], &[0x80, 0x01]);
+
+        let message = Message::parse(&packet).unwrap();
+        let question = &message.questions()[0];
+        assert!(question.prefer_unicast);
+        assert_eq!(question.qclass, QueryClass::IN);
+    }
+
+    #[test]
+    fn parse_unknown_query_type() {
+        // HTTPS query, which `QueryType` doesn't support
+        let mut packet = PacketBuilder::new();
+        packet
+            .add_question(false, "marin.local", QueryClass::IN, QueryType::Unknown(65))
+            .add_question(false, "marin.local", QueryClass::IN, QueryType::A);
+        let packet = packet.build();
+        assert_eq!(&packet[12 + 13..12 + 15], &[0, 65]);
+
+        let message = Message::parse(&packet).unwrap();
+        assert_eq!(message.questions().len(), 2);
+        assert_eq!(message.questions()[0].qtype, QueryType::Unknown(65));
+        assert_eq!(message.questions()[1].qtype, QueryType::A);
+    }
+
+    #[test]
+    fn parse_all_sections() {
+        // response with one answer, one authority and one additional record, whose names are
+        // compressed against the answer.
+        #[rustfmt::skip]
+        let buf = [
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x01,
+            // answer: marin.local A 10.0.0.1
+            5, b'm', b'a', b'r', b'i', b'n', 5, b'l', b'o', b'c', b'a', b'l', 0,
+            0x00, 0x01, 0x80, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x04, 10, 0, 0, 1,
+            // authority: marin.local PTR marin.local
+            0xc0, 12, 0x00, 0x0c, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x02, 0xc0, 12,
+            // additional: local TXT "a=b"
+            0xc0, 18, 0x00, 0x10, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x04, 3, b'a', b'=', b'b',
+        ];
+        let message = Message::parse(&buf).unwrap();
+
+        assert_eq!(message.answers().len(), 1);
+        assert_eq!(message.answers()[0].name(), "marin.local");
+        assert_eq!(message.answers()[0].data(), &RData::a([10, 0, 0, 1].into()));
+
+        assert_eq!(message.authorities().len(), 1);
+        assert_eq!(message.authorities()[0].data(), &RData::ptr("marin.local"));
+
+        assert_eq!(message.additionals().len(), 1);
+        assert_eq!(message.additionals()[0].name(), "local");
+        assert_eq!(message.additionals()[0].data(), &RData::txt(&["a=b"]));
+    }
+
+    #[test]
+    fn parse_negative_response() {
+        // marin.local has an A record, but no AAAA record
+        let mut packet = PacketBuilder::new();
+        packet.header_mut().set_query(false);
+        packet
+            .add_answer(ResourceRecord::IN("marin.local", RData::a([10, 0, 0, 1].into())).unique())
+            .add_additional(
+                ResourceRecord::IN("marin.local", RData::nsec("marin.local", vec![1])).unique(),
+            );
+        let message = Message::parse(&packet.build()).unwrap();
+
+        match message.additionals()[0].data() {
+            RData::NSEC(nsec) => {
+                assert_eq!(nsec.next_domain, "marin.local");
+                assert!(nsec.contains(1));
+                assert!(!nsec.contains(28));
+            }
+            data => panic!("Expected NSEC record, got {:?}", data),
+        }
+    }
+
+    #[test]
+    fn parse_truncated() {
+        let mut packet = PacketBuilder::new();
+        packet.add_answer(ResourceRecord::IN("marin.local", RData::ptr("marin.local")));
+        let packet = packet.build();
+        assert_eq!(
+            Message::parse(&packet[..packet.len() - 1]).unwrap_err(),
+            DecodeError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn parse_borrowed() {
+        let mut packet = PacketBuilder::new();
+        packet.header_mut().set_query(false);
+        packet
+            .add_answer(ResourceRecord::IN(
+                "_myservice._tcp.local",
+                RData::ptr("marin._myservice._tcp.local"),
+            ))
+            .add_additional(ResourceRecord::IN(
+                "marin._myservice._tcp.local",
+                RData::txt(&["path=/"]),
+            ));
+        let packet = packet.build();
+        let message = MessageRef::parse(&packet).unwrap();
+
+        let answers = message.answers().collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(answers.len(), 1);
+        assert_eq!(answers[0].name(), "_myservice._tcp.local");
+        match answers[0].data() {
+            RData::PTR(ptr) => assert_eq!(
+                ptr.0.labels().collect::<Vec<_>>(),
+                &["marin", "_myservice", "_tcp", "local"]
+            ),
+            data => panic!("Expected PTR record, got {:?}", data),
+        }
+        match message.additionals().next().unwrap().unwrap().data() {
+            RData::TXT(txt) => assert!(matches!(txt.0[0], Cow::Borrowed("path=/"))),
+            data => panic!("Expected TXT record, got {:?}", data),
+        }
+        assert_eq!(message.authorities().count(), 0);
+    }
+
+    #[test]
+    fn parse_borrowed_checks_framing() {
+        let mut packet = PacketBuilder::new();
+        packet.add_answer(ResourceRecord::IN(
+            "marin.local",
+            RData::a([10, 0, 0, 1].into()),
+        ));
+        let mut packet = packet.build();
+        // rdata length larger than the rest of the message
+        let len = packet.len();
+        packet[len - 5] = 0x10;
+        assert_eq!(
+            MessageRef::parse(&packet).err(),
+            Some(DecodeError::UnexpectedEof)
+        );
+    }
+
+    #[test]
+    fn invalid_record_stops_iteration() {
+        // two A records, the first one with 3 bytes of rdata
+        #[rustfmt::skip]
+        let buf = [
+            0x00, 0x00, 0x84, 0x00, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00, 0x00,
+            5, b'l', b'o', b'c', b'a', b'l', 0, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78,
+            0x00, 0x03, 10, 0, 0,
+            0xc0, 12, 0x00, 0x01, 0x00, 0x01, 0x00, 0x00, 0x00, 0x78, 0x00, 0x04, 10, 0, 0, 1,
+        ];
+        let message = MessageRef::parse(&buf).unwrap();
+        let mut answers = message.answers();
+        assert_eq!(
+            answers.next().unwrap().err(),
+            Some(DecodeError::UnexpectedEof)
+        );
+        assert!(answers.next().is_none());
+        assert_eq!(
+            Message::parse(&buf).unwrap_err(),
+            DecodeError::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn parse_extended_rcode() {
+        let mut packet = PacketBuilder::new();
+        packet
+            .header_mut()
+            .set_query(false)
+            .set_rcode(RCode::BadVersion);
+        let mut opt = Opt::new(1440);
+        opt.extended_rcode = 1;
+        packet.set_opt(opt);
+        let message = Message::parse(&packet.build()).unwrap();
+        assert_eq!(message.header().rcode(), RCode::NoError);
+        assert_eq!(message.rcode(), RCode::BadVersion);
+    }
+}
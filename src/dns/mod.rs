@@ -1,44 +1,110 @@
+mod error;
 mod header;
+mod message;
+mod name;
 mod packet;
-mod rdata;
+mod presentation;
+pub mod rdata;
+mod reader;
 mod resource_record;
+#[cfg(feature = "serde")]
+pub(crate) mod serialization;
 mod traits;
+mod update;
+mod writer;
+pub mod zone;
 
-pub use dns_parser::Class;
-pub use packet::{PacketBuilder, QueryClass, QueryType};
-pub use resource_record::{RData, ResourceRecord};
-use std::time::Duration;
+use core::time::Duration;
+pub use error::{DecodeError, EncodeError, ParseError, ZoneError};
+pub use header::{OpCode, PacketHeader, RCode};
+pub use message::{Message, MessageRef, Questions, Records};
+pub use name::Name;
+pub use packet::{PacketBuilder, QueryClass, QueryType, Question};
+pub use presentation::parse_records;
+pub use resource_record::{Class, RData, ResourceRecord};
+pub use traits::AppendBytes;
+pub use update::{Prerequisite, Prerequisites, Update, Updates};
+pub use writer::Writer;
 
-fn append_u16(out: &mut Vec<u8>, value: u16) {
+fn append_u16(out: &mut Writer, value: u16) {
     out.push(((value >> 8) & 0xff) as u8);
     out.push((value & 0xff) as u8);
 }
 
-fn append_u32(out: &mut Vec<u8>, value: u32) {
+fn append_u32(out: &mut Writer, value: u32) {
     out.push(((value >> 24) & 0xff) as u8);
     out.push(((value >> 16) & 0xff) as u8);
     out.push(((value >> 8) & 0xff) as u8);
     out.push((value & 0xff) as u8);
 }
 
-fn append_qname(out: &mut Vec<u8>, name: &[u8]) {
-    debug_assert!(name.is_ascii());
+/// Maximum length of an encoded domain name, as defined by RFC 1035 §2.3.4.
+const MAX_NAME_LEN: usize = 255;
 
-    for element in name.split(|&c| c == b'.') {
-        assert!(element.len() < 64, "Service name has a label too long");
-        assert_ne!(element.len(), 0, "Service name contains zero length label");
-        out.push(element.len() as u8);
-        for chr in element.iter() {
-            out.push(*chr);
+/// Maximum length of a label, as defined by RFC 1035 §2.3.4.
+const MAX_LABEL_LEN: usize = 63;
+
+/// Maximum length of the DNS message of an mDNS packet. RFC 6762 §17 limits packets to 9000 bytes,
+/// including the IP and UDP headers, which take up to 48 bytes over IPv6.
+const MAX_PACKET_SIZE: usize = 9000 - 48;
+
+/// Appends a domain name to `out`. If the writer has already written one of the suffixes of the
+/// name, the rest of the name is replaced by a compression pointer.
+///
+/// Labels must be non-empty and at most 63 bytes long, and the whole name at most 255 bytes long
+/// once encoded.
+fn append_qname(out: &mut Writer, name: &Name) -> Result<(), EncodeError> {
+    let mut wire = [0; MAX_NAME_LEN];
+    let mut len = 0;
+    for label in name.labels() {
+        if label.is_empty() {
+            return Err(EncodeError::EmptyLabel);
+        }
+        if label.len() > MAX_LABEL_LEN {
+            return Err(EncodeError::LabelTooLong);
         }
+        // keep room for the root label
+        if len + 1 + label.len() >= MAX_NAME_LEN {
+            return Err(EncodeError::NameTooLong);
+        }
+        wire[len] = label.len() as u8;
+        wire[len + 1..len + 1 + label.len()].copy_from_slice(label.as_bytes());
+        len += 1 + label.len();
+    }
+    let wire = &wire[..len + 1];
+
+    // every suffix of the name starts at a label boundary of its wire form
+    let mut idx = 0;
+    while wire[idx] != 0 {
+        let suffix = &wire[idx..];
+        if let Some(offset) = out.name_offset(suffix) {
+            append_u16(out, 0xc000 | offset);
+            return Ok(());
+        }
+        out.insert_name(suffix);
+
+        let len = wire[idx] as usize;
+        out.extend_from_slice(&wire[idx..idx + 1 + len]);
+        idx += 1 + len;
     }
 
     out.push(0);
+    Ok(())
+}
+
+/// Appends a character string, that is a string prefixed by its length, to `out`.
+fn append_character_string(out: &mut Writer, s: &[u8]) -> Result<(), EncodeError> {
+    if s.len() > u8::MAX as usize {
+        return Err(EncodeError::StringTooLong);
+    }
+    out.push(s.len() as u8);
+    out.extend_from_slice(s);
+    Ok(())
 }
 
 fn duration_to_secs(duration: Duration) -> u32 {
     let secs = duration
         .as_secs()
         .saturating_add(if duration.subsec_nanos() > 0 { 1 } else { 0 });
-    std::cmp::min(secs, From::from(u32::max_value())) as u32
+    core::cmp::min(secs, From::from(u32::MAX)) as u32
 }
@@ -0,0 +1,403 @@
+use alloc::borrow::Cow;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::str::FromStr;
+
+use super::error::ParseError;
+
+/// A domain name, stored as a list of labels.
+///
+/// Names are parsed from their dotted text form, in which a `.` that is part of a label is
+/// escaped as `\.`, and a `\` as `\\`. Any octet can also be escaped as `\DDD`, where `DDD` is its
+/// decimal value, as in the presentation format of RFC 1035 §5.1. A trailing dot is accepted, and
+/// the empty name is the root. Labels may contain any UTF-8 character, as allowed by RFC 6762 §16.
+///
+/// Parsing a name with `str::parse` fails if the octets of a label are not valid UTF-8, while the
+/// `From` conversions replace them with U+FFFD.
+///
+/// Names are compared case-insensitively for ASCII letters, as DNS names are.
+///
+/// A name decoded from a message may borrow the message, and is only decompressed when its labels
+/// are read.
+#[derive(Clone)]
+pub struct Name<'a> {
+    repr: Repr<'a>,
+}
+
+#[derive(Clone)]
+enum Repr<'a> {
+    Labels(Vec<Cow<'a, str>>),
+    /// A name in the wire format of `message`, starting at `offset`. The name has been checked
+    /// when it was decoded.
+    Wire {
+        message: &'a [u8],
+        offset: usize,
+    },
+}
+
+impl Default for Name<'_> {
+    fn default() -> Self {
+        Self {
+            repr: Repr::Labels(Vec::new()),
+        }
+    }
+}
+
+impl<'a> Name<'a> {
+    /// Creates a name from its labels, which are not escaped.
+    pub fn from_labels<S: Into<Cow<'a, str>>>(labels: impl IntoIterator<Item = S>) -> Self {
+        Self {
+            repr: Repr::Labels(labels.into_iter().map(Into::into).collect()),
+        }
+    }
+
+    /// Creates a name from its wire format, at `offset` in `message`. The name must have been
+    /// checked by `Reader::read_name`.
+    pub(crate) fn from_wire(message: &'a [u8], offset: usize) -> Self {
+        Self {
+            repr: Repr::Wire { message, offset },
+        }
+    }
+
+    /// Returns the root name.
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    pub fn is_root(&self) -> bool {
+        self.labels().next().is_none()
+    }
+
+    /// Returns the labels of the name, unescaped. Labels of a compressed name are decompressed as
+    /// they are read.
+    pub fn labels(&self) -> impl Iterator<Item = &str> {
+        let (labels, wire) = match &self.repr {
+            Repr::Labels(labels) => (Some(labels.iter().map(|label| &**label)), None),
+            Repr::Wire { message, offset } => (
+                None,
+                Some(WireLabels {
+                    message,
+                    pos: *offset,
+                }),
+            ),
+        };
+        labels
+            .into_iter()
+            .flatten()
+            .chain(wire.into_iter().flatten())
+    }
+
+    /// Converts the name into a name that owns its labels.
+    pub fn into_owned(self) -> Name<'static> {
+        match self.repr {
+            Repr::Labels(labels) => Name {
+                repr: Repr::Labels(
+                    labels
+                        .into_iter()
+                        .map(|label| Cow::Owned(label.into_owned()))
+                        .collect(),
+                ),
+            },
+            Repr::Wire { .. } => Name::from_labels(
+                self.labels()
+                    .map(|label| label.to_string())
+                    .collect::<Vec<_>>(),
+            ),
+        }
+    }
+
+    /// Returns a name borrowing its labels from `self`.
+    pub fn borrowed(&self) -> Name<'_> {
+        match self.repr {
+            Repr::Labels(_) => Name::from_labels(self.labels()),
+            Repr::Wire { message, offset } => Name::from_wire(message, offset),
+        }
+    }
+
+    /// Parses a name from its dotted text form. `\DDD` escapes must be decimal octets, and the
+    /// octets of a label that contains them must be valid UTF-8, unless `lossy` is set, in which
+    /// case invalid escapes and sequences are replaced with U+FFFD.
+    fn parse(s: &'a str, lossy: bool) -> Result<Self, ParseError> {
+        let mut labels = Vec::new();
+        if s == "." {
+            return Ok(Name::from_labels(labels));
+        }
+
+        let decode = |label: Vec<u8>| match String::from_utf8(label) {
+            Ok(label) => Ok(Cow::Owned(label)),
+            Err(error) if lossy => Ok(Cow::Owned(
+                String::from_utf8_lossy(error.as_bytes()).into_owned(),
+            )),
+            Err(_) => Err(ParseError::InvalidName(s.to_string())),
+        };
+
+        let mut start = 0;
+        // octets of the unescaped label, only allocated when the label contains an escape sequence
+        let mut owned: Option<Vec<u8>> = None;
+        let mut chars = s.char_indices();
+        while let Some((idx, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    let label = owned.get_or_insert_with(|| s.as_bytes()[start..idx].to_vec());
+                    let digits = s[idx + 1..]
+                        .bytes()
+                        .take(3)
+                        .take_while(u8::is_ascii_digit)
+                        .count();
+                    if digits == 0 {
+                        if let Some((_, escaped)) = chars.next() {
+                            push_char(label, escaped);
+                        }
+                        continue;
+                    }
+                    // a digit starts a `\DDD` escape, which must be a decimal octet
+                    match s[idx + 1..idx + 1 + digits].parse::<u8>() {
+                        Ok(byte) if digits == 3 => label.push(byte),
+                        _ if lossy => push_char(label, char::REPLACEMENT_CHARACTER),
+                        _ => return Err(ParseError::InvalidName(s.to_string())),
+                    }
+                    chars.nth(digits - 1);
+                }
+                '.' => {
+                    let label = match owned.take() {
+                        Some(label) => decode(label)?,
+                        None => Cow::Borrowed(&s[start..idx]),
+                    };
+                    labels.push(label);
+                    start = idx + 1;
+                }
+                c => {
+                    if let Some(label) = owned.as_mut() {
+                        push_char(label, c);
+                    }
+                }
+            }
+        }
+
+        // a trailing dot doesn't add an empty label
+        match owned {
+            Some(label) => labels.push(decode(label)?),
+            None if start < s.len() => labels.push(Cow::Borrowed(&s[start..])),
+            None => (),
+        }
+
+        Ok(Name::from_labels(labels))
+    }
+
+    /// Parses a name, replacing the octets of labels that are not valid UTF-8 with U+FFFD.
+    fn parse_lossy(s: &'a str) -> Self {
+        match Name::parse(s, true) {
+            Ok(name) => name,
+            Err(_) => unreachable!("lossy parsing can't fail"),
+        }
+    }
+}
+
+fn push_char(label: &mut Vec<u8>, c: char) {
+    label.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+}
+
+/// Iterator over the labels of a name in wire format, following compression pointers.
+///
+/// Pointers must point strictly backwards, so that iterating always terminates, even over a name
+/// that has not been checked.
+struct WireLabels<'a> {
+    message: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for WireLabels<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        loop {
+            let len = *self.message.get(self.pos)? as usize;
+            match len & 0xc0 {
+                0x00 if len > 0 => {
+                    let label = self.message.get(self.pos + 1..self.pos + 1 + len)?;
+                    self.pos += 1 + len;
+                    return core::str::from_utf8(label).ok();
+                }
+                0xc0 => {
+                    let low = *self.message.get(self.pos + 1)? as usize;
+                    let target = (len & 0x3f) << 8 | low;
+                    if target >= self.pos {
+                        return None;
+                    }
+                    self.pos = target;
+                }
+                _ => return None,
+            }
+        }
+    }
+}
+
+impl<'a> From<&'a str> for Name<'a> {
+    fn from(s: &'a str) -> Self {
+        Name::parse_lossy(s)
+    }
+}
+
+impl<'a> From<&'a String> for Name<'a> {
+    fn from(s: &'a String) -> Self {
+        Name::parse_lossy(s)
+    }
+}
+
+impl From<String> for Name<'static> {
+    fn from(s: String) -> Self {
+        Name::parse_lossy(&s).into_owned()
+    }
+}
+
+impl FromStr for Name<'static> {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Name::parse(s, false).map(Name::into_owned)
+    }
+}
+
+impl<'a> From<&'a Name<'_>> for Name<'a> {
+    fn from(name: &'a Name<'_>) -> Self {
+        name.borrowed()
+    }
+}
+
+impl fmt::Display for Name<'_> {
+    /// Writes the name in its dotted form, without the trailing dot, escaping dots and
+    /// backslashes inside labels.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, label) in self.labels().enumerate() {
+            if i > 0 {
+                f.write_str(".")?;
+            }
+            for c in label.chars() {
+                if c == '.' || c == '\\' {
+                    f.write_str("\\")?;
+                }
+                write!(f, "{}", c)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Name<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Name({:?})", self.to_string())
+    }
+}
+
+impl Name<'_> {
+    fn eq_ignore_case(&self, other: &Name<'_>) -> bool {
+        let mut labels = self.labels();
+        let mut other = other.labels();
+        loop {
+            match (labels.next(), other.next()) {
+                (None, None) => return true,
+                (Some(a), Some(b)) if a.eq_ignore_ascii_case(b) => (),
+                _ => return false,
+            }
+        }
+    }
+}
+
+impl PartialEq for Name<'_> {
+    fn eq(&self, other: &Name<'_>) -> bool {
+        self.eq_ignore_case(other)
+    }
+}
+
+impl Eq for Name<'_> {}
+
+impl PartialEq<str> for Name<'_> {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_ignore_case(&Name::from(other))
+    }
+}
+
+impl PartialEq<&str> for Name<'_> {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_ignore_case(&Name::from(*other))
+    }
+}
+
+impl Hash for Name<'_> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let mut count = 0;
+        for label in self.labels() {
+            state.write_usize(label.len());
+            for b in label.bytes() {
+                state.write_u8(b.to_ascii_lowercase());
+            }
+            count += 1;
+        }
+        state.write_usize(count);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn parse_escaped_labels() {
+        let name = Name::from(r"Marin's Mac 2\.0._myservice._tcp.local.");
+        assert_eq!(
+            name.labels().collect::<Vec<_>>(),
+            &["Marin's Mac 2.0", "_myservice", "_tcp", "local"]
+        );
+        assert_eq!(name.to_string(), r"Marin's Mac 2\.0._myservice._tcp.local");
+
+        let name = Name::from(r"back\\slash.local");
+        assert_eq!(name.labels().next(), Some(r"back\slash"));
+        assert_eq!(name.to_string(), r"back\\slash.local");
+
+        let name = Name::from(r"Marin's\032Mac\046local.local.");
+        assert_eq!(name.labels().next(), Some("Marin's Mac.local"));
+    }
+
+    #[test]
+    fn parse_escaped_octets() {
+        let name: Name = r"caf\195\169.local.".parse().unwrap();
+        assert_eq!(name.labels().next(), Some("café"));
+
+        assert_eq!(
+            r"caf\195.local".parse::<Name>(),
+            Err(ParseError::InvalidName(r"caf\195.local".to_string()))
+        );
+        let name = Name::from(r"caf\195.local");
+        assert_eq!(name.labels().next(), Some("caf\u{fffd}"));
+
+        // escapes of more than 255, or of less than three digits, are not octets
+        for invalid in [r"caf\999.local", r"caf\25.local", r"caf\2"].iter() {
+            assert_eq!(
+                invalid.parse::<Name>(),
+                Err(ParseError::InvalidName(invalid.to_string()))
+            );
+        }
+        let name = Name::from(r"caf\999e.local");
+        assert_eq!(name.labels().next(), Some("caf\u{fffd}e"));
+    }
+
+    #[test]
+    fn parse_root() {
+        assert!(Name::from("").is_root());
+        assert!(Name::from(".").is_root());
+        assert_eq!(Name::root().to_string(), "");
+    }
+
+    #[test]
+    fn case_insensitive_eq_and_hash() {
+        let mut names = HashSet::new();
+        names.insert(Name::from("_MyService._tcp.local"));
+        assert!(names.contains(&Name::from("_myservice._TCP.local.")));
+        assert_eq!(Name::from("MARIN.local"), "marin.local");
+        assert_ne!(Name::from("marin.local"), "marin.lan");
+        assert_ne!(Name::from("a.b.local"), Name::from(r"a\.b.local"));
+    }
+}
@@ -1,21 +1,212 @@
-use super::header::PacketHeader;
+use super::error::{DecodeError, EncodeError};
+use super::header::{OpCode, PacketHeader};
+use super::rdata::opt::Record as Opt;
+use super::reader::{ReadBytes, Reader};
 use super::traits::AppendBytes;
-use super::ResourceRecord;
-use super::{append_qname, append_u16};
-pub use dns_parser::{QueryClass, QueryType};
+use super::update::{Prerequisite, Update};
+use super::writer::{self, to_vec, Writer};
+use super::{append_qname, append_u16, MAX_PACKET_SIZE};
+use super::{Class, Name, ResourceRecord};
+use alloc::vec;
+use alloc::vec::Vec;
+use bytes::BufMut;
+use core::convert::TryFrom;
 
-struct Question<'a> {
-    pub name: &'a str,
+/// The type of a question (RFC 1035 §3.2.3).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryType {
+    /// A host address.
+    A,
+    /// An authoritative name server.
+    NS,
+    /// A mail forwarder, obsoleted by MX.
+    MF,
+    /// The canonical name of an alias.
+    CNAME,
+    /// The start of a zone of authority.
+    SOA,
+    /// A mailbox domain name.
+    MB,
+    /// A mail group member.
+    MG,
+    /// A mail rename domain name.
+    MR,
+    /// A null record.
+    NULL,
+    /// A well known service description.
+    WKS,
+    /// A domain name pointer.
+    PTR,
+    /// Host information.
+    HINFO,
+    /// Mailbox or mail list information.
+    MINFO,
+    /// Mail exchange.
+    MX,
+    /// Text strings.
+    TXT,
+    /// An IPv6 host address (RFC 3596).
+    AAAA,
+    /// A service location (RFC 2782).
+    SRV,
+    /// The types of records that exist for a name (RFC 4034).
+    NSEC,
+    /// A transfer of an entire zone.
+    AXFR,
+    /// Mailbox-related records (MB, MG or MR).
+    MAILB,
+    /// Mail agent records, obsoleted by MX.
+    MAILA,
+    /// All records.
+    All,
+    /// A type that is not supported, kept as its code.
+    Unknown(u16),
+}
+
+impl QueryType {
+    pub fn parse(code: u16) -> Self {
+        use QueryType::*;
+        match code {
+            1 => A,
+            2 => NS,
+            3 => MF,
+            5 => CNAME,
+            6 => SOA,
+            7 => MB,
+            8 => MG,
+            9 => MR,
+            10 => NULL,
+            11 => WKS,
+            12 => PTR,
+            13 => HINFO,
+            14 => MINFO,
+            15 => MX,
+            16 => TXT,
+            28 => AAAA,
+            33 => SRV,
+            47 => NSEC,
+            252 => AXFR,
+            253 => MAILB,
+            254 => MAILA,
+            255 => All,
+            _ => Unknown(code),
+        }
+    }
+
+    /// Returns the code of the type.
+    pub fn code(self) -> u16 {
+        use QueryType::*;
+        match self {
+            A => 1,
+            NS => 2,
+            MF => 3,
+            CNAME => 5,
+            SOA => 6,
+            MB => 7,
+            MG => 8,
+            MR => 9,
+            NULL => 10,
+            WKS => 11,
+            PTR => 12,
+            HINFO => 13,
+            MINFO => 14,
+            MX => 15,
+            TXT => 16,
+            AAAA => 28,
+            SRV => 33,
+            NSEC => 47,
+            AXFR => 252,
+            MAILB => 253,
+            MAILA => 254,
+            All => 255,
+            Unknown(code) => code,
+        }
+    }
+}
+
+/// The class of a question (RFC 1035 §3.2.5).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryClass {
+    /// The Internet.
+    IN = 1,
+    /// The CSNET class, which is obsolete.
+    CS = 2,
+    /// The CHAOS class.
+    CH = 3,
+    /// Hesiod.
+    HS = 4,
+    /// Any class.
+    Any = 255,
+}
+
+impl TryFrom<Class> for QueryClass {
+    type Error = DecodeError;
+
+    fn try_from(class: Class) -> Result<Self, Self::Error> {
+        QueryClass::parse(class.code())
+    }
+}
+
+impl QueryClass {
+    pub fn parse(code: u16) -> Result<Self, DecodeError> {
+        match code {
+            1 => Ok(QueryClass::IN),
+            2 => Ok(QueryClass::CS),
+            3 => Ok(QueryClass::CH),
+            4 => Ok(QueryClass::HS),
+            255 => Ok(QueryClass::Any),
+            _ => Err(DecodeError::InvalidQueryClass(code)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Question<'a> {
+    pub name: Name<'a>,
     pub prefer_unicast: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::qtype"))]
     pub qtype: QueryType,
+    #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::qclass"))]
     pub qclass: QueryClass,
 }
 
+impl Question<'_> {
+    /// Converts the question into a question that owns its name.
+    pub fn into_owned(self) -> Question<'static> {
+        Question {
+            name: self.name.into_owned(),
+            prefer_unicast: self.prefer_unicast,
+            qtype: self.qtype,
+            qclass: self.qclass,
+        }
+    }
+}
+
 impl AppendBytes for Question<'_> {
-    fn append_bytes(&self, out: &mut Vec<u8>) {
-        append_qname(out, self.name.as_bytes());
-        append_u16(out, self.qtype as u16);
-        append_u16(out, self.qclass as u16);
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        append_qname(out, &self.name)?;
+        append_u16(out, self.qtype.code());
+        // the top bit of the class is the mDNS unicast-response bit
+        let unicast_bit = if self.prefer_unicast { 0x8000 } else { 0 };
+        append_u16(out, self.qclass as u16 | unicast_bit);
+        Ok(())
+    }
+}
+
+impl<'a> ReadBytes<'a> for Question<'a> {
+    fn read_bytes(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let name = reader.read_name()?;
+        let qtype = reader.read_u16()?;
+        let qclass = reader.read_u16()?;
+        Ok(Self {
+            name,
+            // the top bit of the class is the mDNS unicast-response bit
+            prefer_unicast: qclass & 0x8000 != 0,
+            qtype: QueryType::parse(qtype),
+            qclass: QueryClass::parse(qclass & 0x7fff)
+                .map_err(|_| DecodeError::InvalidQueryClass(qclass))?,
+        })
     }
 }
 
@@ -23,6 +214,18 @@ pub struct PacketBuilder<'a> {
     header: PacketHeader,
     questions: Vec<Question<'a>>,
     answers: Vec<ResourceRecord<'a>>,
+    authorities: Vec<ResourceRecord<'a>>,
+    additionals: Vec<ResourceRecord<'a>>,
+    opt: Option<ResourceRecord<'a>>,
+    prerequisites: Vec<Prerequisite<'a>>,
+    updates: Vec<Update<'a>>,
+    compress: bool,
+}
+
+impl Default for PacketBuilder<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 // Builder for mDNS packets
@@ -33,9 +236,24 @@ impl<'a> PacketBuilder<'a> {
             header: PacketHeader::default(),
             questions: Vec::new(),
             answers: Vec::new(),
+            authorities: Vec::new(),
+            additionals: Vec::new(),
+            opt: None,
+            prerequisites: Vec::new(),
+            updates: Vec::new(),
+            compress: true,
         }
     }
 
+    /// Creates a builder for a DNS UPDATE message (RFC 2136) modifying `zone`. The zone is
+    /// written as the only question of the message, with the SOA type.
+    pub fn update(zone: impl Into<Name<'a>>, class: QueryClass) -> Self {
+        let mut builder = Self::new();
+        builder.header.set_opcode(OpCode::UPDATE);
+        builder.add_question(false, zone, class, QueryType::SOA);
+        builder
+    }
+
     /// Returns a reference to the header of the packet.
     pub fn header(&self) -> &PacketHeader {
         &self.header
@@ -46,16 +264,24 @@ impl<'a> PacketBuilder<'a> {
         &mut self.header
     }
 
+    /// Enables or disables name compression. When enabled, names, and PTR and SRV targets,
+    /// that repeat a name already present in the packet are replaced by a pointer to it, as
+    /// described in RFC 1035 §4.1.4. Defaults to true.
+    pub fn set_compression(&mut self, enabled: bool) -> &mut Self {
+        self.compress = enabled;
+        self
+    }
+
     /// Add a question to the packet
     pub fn add_question(
         &mut self,
         prefer_unicast: bool,
-        name: &'a str,
+        name: impl Into<Name<'a>>,
         qclass: QueryClass,
         qtype: QueryType,
     ) -> &mut Self {
         self.questions.push(Question {
-            name,
+            name: name.into(),
             prefer_unicast,
             qtype,
             qclass,
@@ -71,17 +297,221 @@ impl<'a> PacketBuilder<'a> {
         self
     }
 
+    /// Adds a record to the authority section of the packet. mDNS probes use this section to
+    /// carry the records they propose to claim.
+    pub fn add_authority(&mut self, record: ResourceRecord<'a>) -> &mut Self {
+        self.authorities.push(record);
+        self.header.ns_count += 1;
+        self
+    }
+
+    /// Adds a record to the additional section of the packet, such as the SRV, TXT and address
+    /// records that accompany a PTR answer in DNS-SD.
+    pub fn add_additional(&mut self, record: ResourceRecord<'a>) -> &mut Self {
+        self.additionals.push(record);
+        self.header.ar_count += 1;
+        self
+    }
+
+    /// Adds a prerequisite to the answer section of an UPDATE message.
+    pub fn add_prerequisite(&mut self, prerequisite: Prerequisite<'a>) -> &mut Self {
+        self.prerequisites.push(prerequisite);
+        self.header.an_count += 1;
+        self
+    }
+
+    /// Adds an update to the authority section of an UPDATE message.
+    pub fn add_update(&mut self, update: Update<'a>) -> &mut Self {
+        self.updates.push(update);
+        self.header.ns_count += 1;
+        self
+    }
+
+    /// Sets the EDNS0 OPT pseudo-record of the packet. It is written after every other record of
+    /// the additional section, and replaces any OPT record previously set.
+    pub fn set_opt(&mut self, opt: Opt<'a>) -> &mut Self {
+        if self.opt.is_none() {
+            self.header.ar_count += 1;
+        }
+        self.opt = Some(ResourceRecord::opt(opt));
+        self
+    }
+
     /// Builds the packet and returns the bytes for that packet.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the packet can't be encoded. See `try_build` for a non-panicking version.
     pub fn build(self) -> Vec<u8> {
-        let mut buffer = Vec::with_capacity(4096);
-        self.header.append_bytes(&mut buffer);
-        self.questions
-            .iter()
-            .for_each(|q| q.append_bytes(&mut buffer));
-        self.answers
-            .iter()
-            .for_each(|q| q.append_bytes(&mut buffer));
-        buffer
+        self.try_build()
+            .unwrap_or_else(|e| panic!("failed to build packet: {}", e))
+    }
+
+    /// Builds the packet and returns the bytes for that packet, or an error if a name, a TXT
+    /// string or the packet itself is too long, or if a name contains an empty label.
+    pub fn try_build(self) -> Result<Vec<u8>, EncodeError> {
+        let mut out = Writer::growable(self.compress);
+        self.write(&mut out)?;
+        Ok(out.into_vec())
+    }
+
+    /// Returns the length of the packet once encoded, or an error if it can't be encoded. The
+    /// packet is encoded without keeping its bytes.
+    pub fn encoded_len(&self) -> Result<usize, EncodeError> {
+        let mut out = Writer::counting(self.compress);
+        self.write(&mut out)?;
+        Ok(out.len())
+    }
+
+    /// Builds the packet into `buf`, without allocating, and returns its length. Returns
+    /// `EncodeError::BufferTooSmall` with the length of the packet if it doesn't fit in `buf`.
+    pub fn build_into(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        writer::write_into(buf, self.compress, |out| self.write(out))
+    }
+
+    /// Builds the packet at the end of `buf`, and returns its length. Returns
+    /// `EncodeError::BufferTooSmall` with the length of the packet if the remaining capacity of
+    /// `buf` is too small.
+    ///
+    /// The packet is encoded straight into the spare capacity of `buf`, and only allocates if it
+    /// doesn't fit in the current chunk of `buf`.
+    pub fn build_into_buf(&self, buf: &mut impl BufMut) -> Result<usize, EncodeError> {
+        writer::write_into_buf(buf, self.compress, |out| self.write(out))
+    }
+
+    /// Writes the header, then every question and record of the packet. Returns an error if the
+    /// packet is longer than the maximum mDNS packet size.
+    fn write(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        self.header.append_bytes(out)?;
+        for question in &self.questions {
+            question.append_bytes(out)?;
+        }
+        for record in &self.answers {
+            record.append_bytes(out)?;
+        }
+        for prerequisite in &self.prerequisites {
+            prerequisite.append_bytes(out)?;
+        }
+        for record in &self.authorities {
+            record.append_bytes(out)?;
+        }
+        for update in &self.updates {
+            update.append_bytes(out)?;
+        }
+        for record in self.additionals.iter().chain(self.opt.iter()) {
+            record.append_bytes(out)?;
+        }
+        if out.len() > MAX_PACKET_SIZE {
+            return Err(EncodeError::PacketTooLong(out.len()));
+        }
+        Ok(())
+    }
+
+    /// Builds the packet, splitting it into as many packets of at most `max_size` bytes as
+    /// needed. `max_size` is capped to the maximum mDNS packet size.
+    ///
+    /// In a response, the records of every section are spread over the packets, in order. In a
+    /// query, the questions and the authority records stay in the first packet, and the known
+    /// answers are split over the following ones. Every packet but the last then has the `TC` bit
+    /// set, as described in RFC 6762 §7.2.
+    ///
+    /// The prerequisites and updates of an UPDATE message stay in the first packet, as an update
+    /// is applied as a whole. The OPT record, if any, is repeated at the end of every packet.
+    ///
+    /// Returns an error if a record can't be encoded, or if a packet can't fit even a single
+    /// record.
+    pub fn build_many(self, max_size: usize) -> Result<Vec<Vec<u8>>, EncodeError> {
+        let max_size = max_size.min(MAX_PACKET_SIZE);
+        // room left at the end of every packet for the OPT record
+        let max_size = match &self.opt {
+            Some(opt) => max_size.saturating_sub(to_vec(opt)?.len()),
+            None => max_size,
+        };
+        let is_query = self.header.is_query();
+        let mut packets = Vec::new();
+
+        let mut buffer = Writer::growable(self.compress);
+        self.header.append_bytes(&mut buffer)?;
+        let mut counts = [0; 4];
+        for question in &self.questions {
+            question.append_bytes(&mut buffer)?;
+            counts[0] += 1;
+        }
+        for prerequisite in &self.prerequisites {
+            prerequisite.append_bytes(&mut buffer)?;
+            counts[1] += 1;
+        }
+        if is_query {
+            for record in &self.authorities {
+                record.append_bytes(&mut buffer)?;
+                counts[2] += 1;
+            }
+        }
+        for update in &self.updates {
+            update.append_bytes(&mut buffer)?;
+            counts[2] += 1;
+        }
+        if buffer.len() > max_size {
+            return Err(EncodeError::PacketTooLong(buffer.len()));
+        }
+
+        let mut sections = vec![(1, &self.answers)];
+        if !is_query {
+            sections.push((2, &self.authorities));
+        }
+        sections.push((3, &self.additionals));
+
+        for (section, records) in sections {
+            for record in records {
+                let len = buffer.len();
+                record.append_bytes(&mut buffer)?;
+                if buffer.len() > max_size {
+                    if counts.iter().all(|&count| count == 0) {
+                        return Err(EncodeError::PacketTooLong(buffer.len()));
+                    }
+                    buffer.truncate(len);
+                    packets.push(self.finish_packet(&mut buffer, counts, is_query)?);
+                    counts = [0; 4];
+                    buffer.truncate(0);
+                    self.header.append_bytes(&mut buffer)?;
+
+                    record.append_bytes(&mut buffer)?;
+                    if buffer.len() > max_size {
+                        return Err(EncodeError::PacketTooLong(buffer.len()));
+                    }
+                }
+                counts[section] += 1;
+            }
+        }
+
+        packets.push(self.finish_packet(&mut buffer, counts, false)?);
+        Ok(packets)
+    }
+
+    /// Appends the OPT record to a packet built by `build_many`, rewrites its header with the
+    /// section counts of that packet, and returns a copy of it.
+    fn finish_packet(
+        &self,
+        buffer: &mut Writer,
+        mut counts: [u16; 4],
+        truncated: bool,
+    ) -> Result<Vec<u8>, EncodeError> {
+        if let Some(opt) = &self.opt {
+            opt.append_bytes(buffer)?;
+            counts[3] += 1;
+        }
+
+        let mut header = self.header.clone();
+        header.qd_count = counts[0];
+        header.an_count = counts[1];
+        header.ns_count = counts[2];
+        header.ar_count = counts[3];
+        header.set_tc(truncated);
+
+        let mut header_bytes = [0; 12];
+        header.append_bytes(&mut Writer::new(&mut header_bytes, false))?;
+        buffer.write_at(0, &header_bytes);
+        Ok(buffer.to_vec())
     }
 }
 
@@ -90,32 +520,34 @@ mod test {
     use super::super::rdata::a::Record as A;
     use super::super::rdata::aaaa::Record as AAAA;
     use super::super::rdata::txt::Record as Txt;
+    use super::super::Class;
+    use super::super::{Message, RData};
     use super::*;
-    use dns_parser::Class;
-    use dns_parser::Packet;
-    use mdns::RecordKind;
-    use std::net::{Ipv4Addr, Ipv6Addr};
-    use std::time::Duration;
+    use core::net::{Ipv4Addr, Ipv6Addr};
+    use core::time::Duration;
 
     #[test]
     fn build_packet() {
         let answer1 = ResourceRecord {
-            name: "_service._tcp.local",
+            name: Name::from("_service._tcp.local"),
             ttl: Duration::from_secs(4500),
             class: Class::IN,
-            data: crate::dns::RData::A(A(Ipv4Addr::new(7, 123, 234, 1))),
+            cache_flush: false,
+            data: RData::A(A(Ipv4Addr::new(7, 123, 234, 1))),
         };
         let answer2 = ResourceRecord {
-            name: "_service._tcp.local",
+            name: Name::from("_service._tcp.local"),
             ttl: Duration::from_secs(4500),
             class: Class::IN,
-            data: crate::dns::RData::TXT(Txt(&["foo=bar", "baz=qux", "foobar"])),
+            cache_flush: false,
+            data: RData::txt(&["foo=bar", "baz=qux", "foobar"]),
         };
         let answer3 = ResourceRecord {
-            name: "_service._tcp.local",
+            name: Name::from("_service._tcp.local"),
             ttl: Duration::from_secs(4500),
             class: Class::IN,
-            data: crate::dns::RData::AAAA(AAAA(Ipv6Addr::new(
+            cache_flush: false,
+            data: RData::AAAA(AAAA(Ipv6Addr::new(
                 0xabcd, 0x4391, 0xd53a, 0x98dd, 0x7a4f, 0x0000, 0xffff, 0x0123,
             ))),
         };
@@ -128,14 +560,332 @@ mod test {
             .add_answer(answer2)
             .add_answer(answer3);
         let packet = packet.build();
-        let parsed = Packet::parse(&packet).unwrap();
-        let packet = mdns::Response::from_packet(&parsed);
-        println!("{:#?}", packet);
+        let message = Message::parse(&packet).unwrap();
 
-        if let RecordKind::TXT(ref txt) = packet.answers.get(1).unwrap().kind {
+        assert_eq!(message.header().id(), 12);
+        if let RData::TXT(Txt(ref txt)) = message.answers()[1].data() {
             assert_eq!(txt, &["foo=bar", "baz=qux", "foobar"]);
         } else {
             panic!("Expected TXT record");
         }
     }
+
+    fn service_response<'a>() -> PacketBuilder<'a> {
+        let mut packet = PacketBuilder::new();
+        packet
+            .add_answer(ResourceRecord::IN(
+                "_myservice._tcp.local",
+                RData::ptr("marin._myservice._tcp.local"),
+            ))
+            .add_answer(ResourceRecord::IN(
+                "marin._myservice._tcp.local",
+                RData::srv(8594, 0, 0, "marin.local"),
+            ))
+            .add_answer(ResourceRecord::IN(
+                "marin.local",
+                RData::a(Ipv4Addr::new(192, 168, 31, 78)),
+            ));
+        packet
+    }
+
+    #[test]
+    fn build_all_sections() {
+        let mut packet = service_response();
+        packet
+            .add_authority(ResourceRecord::IN(
+                "marin.local",
+                RData::a(Ipv4Addr::new(192, 168, 31, 78)),
+            ))
+            .add_additional(ResourceRecord::IN(
+                "marin._myservice._tcp.local",
+                RData::txt(&["foobar"]),
+            ));
+        assert_eq!(packet.header().ns_count(), 1);
+        assert_eq!(packet.header().ar_count(), 1);
+
+        let message = Message::parse(&packet.build()).unwrap();
+        assert_eq!(message.header().an_count(), 3);
+        assert_eq!(message.answers().len(), 3);
+        assert_eq!(message.authorities().len(), 1);
+        assert_eq!(message.authorities()[0].name(), "marin.local");
+        assert_eq!(message.additionals().len(), 1);
+        assert_eq!(message.additionals()[0].data(), &RData::txt(&["foobar"]));
+    }
+
+    #[test]
+    fn try_build_invalid_names() {
+        let long_label = "a".repeat(64);
+        let long_name = vec!["a"; 128].join(".");
+        for (name, error) in [
+            ("foo..local", EncodeError::EmptyLabel),
+            (long_label.as_str(), EncodeError::LabelTooLong),
+            (long_name.as_str(), EncodeError::NameTooLong),
+        ] {
+            let mut packet = PacketBuilder::new();
+            packet.add_question(false, name, QueryClass::IN, QueryType::PTR);
+            assert_eq!(packet.try_build().unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn try_build_long_txt() {
+        let long_string = "a".repeat(256);
+        let strings = [long_string.as_str()];
+        let mut packet = PacketBuilder::new();
+        packet.add_answer(ResourceRecord::IN("marin.local", RData::txt(&strings)));
+        assert_eq!(packet.try_build().unwrap_err(), EncodeError::StringTooLong);
+    }
+
+    #[test]
+    fn try_build_long_packet() {
+        let strings = vec!["a".repeat(255); 40];
+        let strings = strings.iter().map(String::as_str).collect::<Vec<_>>();
+        let mut packet = PacketBuilder::new();
+        packet.add_answer(ResourceRecord::IN("marin.local", RData::txt(&strings)));
+        assert!(matches!(
+            packet.try_build(),
+            Err(EncodeError::PacketTooLong(_))
+        ));
+
+        // 8980 bytes, which only fits in 9000 bytes without the IP and UDP headers
+        let mut packet = PacketBuilder::new();
+        packet.add_answer(ResourceRecord::IN(
+            "marin.local",
+            RData::null(vec![0; 8945]),
+        ));
+        assert_eq!(packet.encoded_len(), Err(EncodeError::PacketTooLong(8980)));
+    }
+
+    #[test]
+    fn build_many_splits_answers() {
+        let mut packet = service_response();
+        packet.header_mut().set_query(false);
+        for i in 0..20 {
+            packet.add_additional(ResourceRecord::IN(
+                "marin.local",
+                RData::a(Ipv4Addr::new(10, 0, 0, i)),
+            ));
+        }
+        let packets = packet.build_many(200).unwrap();
+        assert!(packets.len() > 1);
+
+        let mut answers = 0;
+        let mut additionals = 0;
+        for packet in &packets {
+            assert!(packet.len() <= 200);
+            let message = Message::parse(packet).unwrap();
+            assert!(!message.header().is_query());
+            assert!(!message.header().tc());
+            answers += message.answers().len();
+            additionals += message.additionals().len();
+        }
+        assert_eq!(answers, 3);
+        assert_eq!(additionals, 20);
+    }
+
+    #[test]
+    fn build_many_truncates_known_answers() {
+        let mut packet = PacketBuilder::new();
+        packet.add_question(
+            false,
+            "_myservice._tcp.local",
+            QueryClass::IN,
+            QueryType::PTR,
+        );
+        let names = (0..20)
+            .map(|i| format!("instance{}._myservice._tcp.local", i))
+            .collect::<Vec<_>>();
+        for name in &names {
+            packet.add_answer(ResourceRecord::IN(
+                "_myservice._tcp.local",
+                RData::ptr(name),
+            ));
+        }
+        let packets = packet.build_many(200).unwrap();
+        assert!(packets.len() > 1);
+
+        let messages = packets
+            .iter()
+            .map(|packet| Message::parse(packet).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(messages[0].questions().len(), 1);
+        let (last, rest) = messages.split_last().unwrap();
+        assert!(rest.iter().all(|message| message.header().tc()));
+        assert!(!last.header().tc());
+        assert!(messages[1..]
+            .iter()
+            .all(|message| message.questions().is_empty()));
+        let known_answers = messages
+            .iter()
+            .map(|message| message.answers().len())
+            .sum::<usize>();
+        assert_eq!(known_answers, 20);
+    }
+
+    #[test]
+    fn build_many_record_too_long() {
+        let packet = service_response();
+        assert!(matches!(
+            packet.build_many(40),
+            Err(EncodeError::PacketTooLong(_))
+        ));
+    }
+
+    #[test]
+    fn build_many_repeats_opt() {
+        let mut packet = service_response();
+        packet.header_mut().set_query(false);
+        for i in 0..20 {
+            packet.add_additional(ResourceRecord::IN(
+                "marin.local",
+                RData::a(Ipv4Addr::new(10, 0, 0, i)),
+            ));
+        }
+        packet.set_opt(Opt::new(1440));
+        let packets = packet.build_many(200).unwrap();
+        assert!(packets.len() > 1);
+
+        for packet in &packets {
+            assert!(packet.len() <= 200);
+            let message = Message::parse(packet).unwrap();
+            let opt = message.opt().unwrap();
+            assert_eq!(opt.udp_payload_size, 1440);
+            assert!(matches!(
+                message.additionals().last().unwrap().data(),
+                RData::OPT(_)
+            ));
+        }
+    }
+
+    #[test]
+    fn build_instance_name_with_dot() {
+        let instance = r"Marin's Mac 2\.0._myservice._tcp.local";
+        let mut packet = PacketBuilder::new();
+        packet.add_answer(ResourceRecord::IN(
+            "_myservice._tcp.local",
+            RData::ptr(instance),
+        ));
+        let message = Message::parse(&packet.build()).unwrap();
+
+        match message.answers()[0].data() {
+            RData::PTR(ptr) => {
+                assert_eq!(ptr.0.labels().next(), Some("Marin's Mac 2.0"));
+                assert_eq!(ptr.0, instance);
+            }
+            data => panic!("Expected PTR record, got {:?}", data),
+        }
+    }
+
+    #[test]
+    fn compress_names() {
+        let compressed = service_response().build();
+        let mut uncompressed = service_response();
+        uncompressed.set_compression(false);
+        let uncompressed = uncompressed.build();
+        assert!(compressed.len() < uncompressed.len());
+
+        let compressed = Message::parse(&compressed).unwrap();
+        let uncompressed = Message::parse(&uncompressed).unwrap();
+        assert_eq!(compressed.answers(), uncompressed.answers());
+        assert_eq!(
+            compressed.answers()[1].data(),
+            &RData::srv(8594, 0, 0, "marin.local")
+        );
+    }
+
+    #[test]
+    fn build_into_buffer() {
+        let packet = service_response();
+        let expected = service_response().build();
+        assert_eq!(packet.encoded_len(), Ok(expected.len()));
+
+        // the same buffer is reused for every packet
+        let mut buf = [0; 512];
+        for _ in 0..2 {
+            let len = packet.build_into(&mut buf).unwrap();
+            assert_eq!(&buf[..len], &expected[..]);
+        }
+
+        let mut small = [0; 64];
+        assert_eq!(
+            packet.build_into(&mut small),
+            Err(EncodeError::BufferTooSmall(expected.len()))
+        );
+    }
+
+    #[test]
+    fn build_into_buf_mut() {
+        let packet = service_response();
+        let expected = service_response().build();
+
+        let mut buf = bytes::BytesMut::new();
+        buf.put_u8(0xff);
+        assert_eq!(packet.build_into_buf(&mut buf), Ok(expected.len()));
+        assert_eq!(&buf[1..], &expected[..]);
+
+        let mut small = [0; 64];
+        assert_eq!(
+            packet.build_into_buf(&mut &mut small[..]),
+            Err(EncodeError::BufferTooSmall(expected.len()))
+        );
+    }
+
+    /// Counts the allocations of the current thread, to check that encoding doesn't allocate.
+    struct CountingAllocator;
+
+    std::thread_local! {
+        static ALLOCATIONS: core::cell::Cell<usize> = const { core::cell::Cell::new(0) };
+    }
+
+    unsafe impl std::alloc::GlobalAlloc for CountingAllocator {
+        unsafe fn alloc(&self, layout: std::alloc::Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + 1));
+            std::alloc::System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: std::alloc::Layout) {
+            std::alloc::System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    /// Calls `f`, and returns its result with the number of allocations it made.
+    fn count_allocations<T>(f: impl FnOnce() -> T) -> (T, usize) {
+        let before = ALLOCATIONS.with(|count| count.get());
+        let result = f();
+        (result, ALLOCATIONS.with(|count| count.get()) - before)
+    }
+
+    #[test]
+    fn build_without_allocating() {
+        let packet = service_response();
+        let expected = service_response().build();
+
+        // the same buffers are reused for every packet
+        let mut buf = bytes::BytesMut::with_capacity(512);
+        let mut slice = [0; 512];
+        for _ in 0..2 {
+            buf.clear();
+            let built = count_allocations(|| packet.build_into_buf(&mut buf));
+            assert_eq!(built, (Ok(expected.len()), 0));
+            assert_eq!(&buf[..], &expected[..]);
+
+            let built = count_allocations(|| packet.build_into(&mut slice));
+            assert_eq!(built, (Ok(expected.len()), 0));
+            assert_eq!(&slice[..expected.len()], &expected[..]);
+        }
+
+        // records are written on their own the same way
+        let record = ResourceRecord::IN("marin.local", RData::a(Ipv4Addr::new(10, 0, 0, 1)));
+        let expected = to_vec(&record).unwrap();
+        buf.clear();
+        let written = count_allocations(|| record.write_into_buf(&mut buf));
+        assert_eq!(written, (Ok(expected.len()), 0));
+        assert_eq!(&buf[..], &expected[..]);
+        let written = count_allocations(|| record.write_into(&mut slice));
+        assert_eq!(written, (Ok(expected.len()), 0));
+        assert_eq!(&slice[..expected.len()], &expected[..]);
+    }
 }
@@ -0,0 +1,728 @@
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+use core::time::Duration;
+
+use super::error::ParseError;
+use super::message::Message;
+use super::packet::Question;
+use super::rdata::opt::Record as Opt;
+use super::reader::Reader;
+use super::update::{Prerequisite, Update, ANY, NONE};
+use super::writer::to_vec;
+use super::{duration_to_secs, Class, Name, OpCode, RData, ResourceRecord};
+
+/// Mnemonics of the types that can be written in presentation format. Other types are written as
+/// `TYPEnnn` (RFC 3597 §5).
+pub(crate) const TYPES: &[(u16, &str)] = &[
+    (1, "A"),
+    (2, "NS"),
+    (5, "CNAME"),
+    (6, "SOA"),
+    (10, "NULL"),
+    (12, "PTR"),
+    (13, "HINFO"),
+    (15, "MX"),
+    (16, "TXT"),
+    (28, "AAAA"),
+    (33, "SRV"),
+    (41, "OPT"),
+    (47, "NSEC"),
+    (252, "AXFR"),
+    (253, "MAILB"),
+    (254, "MAILA"),
+    (255, "ANY"),
+];
+
+/// Mnemonics of the classes. Other classes are written as `CLASSnnn`.
+pub(crate) const CLASSES: &[(u16, &str)] = &[
+    (1, "IN"),
+    (2, "CS"),
+    (3, "CH"),
+    (4, "HS"),
+    (254, "NONE"),
+    (255, "ANY"),
+];
+
+pub(crate) struct Mnemonic(&'static [(u16, &'static str)], &'static str, u16);
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Mnemonic(table, prefix, value) = *self;
+        match table.iter().find(|(code, _)| *code == value) {
+            Some((_, name)) => f.write_str(name),
+            None => write!(f, "{}{}", prefix, value),
+        }
+    }
+}
+
+pub(crate) fn parse_mnemonic(table: &[(u16, &str)], prefix: &str, s: &str) -> Option<u16> {
+    if let Some((code, _)) = table.iter().find(|(_, name)| name.eq_ignore_ascii_case(s)) {
+        return Some(*code);
+    }
+    match s.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => s[prefix.len()..].parse().ok(),
+        _ => None,
+    }
+}
+
+pub(crate) fn type_name(rtype: u16) -> Mnemonic {
+    Mnemonic(TYPES, "TYPE", rtype)
+}
+
+/// Writes the class, with the mDNS cache-flush or unicast-response bit, as `dig` does.
+pub(crate) fn class_name(class: u16, top_bit: bool) -> Mnemonic {
+    let top_bit = if top_bit { 0x8000 } else { 0 };
+    Mnemonic(CLASSES, "CLASS", class | top_bit)
+}
+
+/// Writes an absolute name, with its trailing dot. Characters that have a meaning in the
+/// presentation format are escaped.
+struct Absolute<'a, 'b>(&'a Name<'b>);
+
+impl fmt::Display for Absolute<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_root() {
+            return f.write_str(".");
+        }
+        for label in self.0.labels() {
+            for c in label.chars() {
+                match c {
+                    '.' | '\\' | '"' | ';' | '(' | ')' | '@' | '$' => write!(f, "\\{}", c)?,
+                    c if c.is_ascii_whitespace() || c.is_ascii_control() => {
+                        write!(f, "\\{:03}", c as u32)?
+                    }
+                    c => write!(f, "{}", c)?,
+                }
+            }
+            f.write_str(".")?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes a character string between quotes.
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("\"")?;
+        for c in self.0.chars() {
+            match c {
+                '"' | '\\' => write!(f, "\\{}", c)?,
+                c if c.is_ascii_control() => write!(f, "\\{:03}", c as u32)?,
+                c => write!(f, "{}", c)?,
+            }
+        }
+        f.write_str("\"")
+    }
+}
+
+/// Writes data in the generic format of RFC 3597 §5.
+struct Generic<'a>(&'a [u8]);
+
+impl fmt::Display for Generic<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\\# {}", self.0.len())?;
+        if !self.0.is_empty() {
+            f.write_str(" ")?;
+            for b in self.0 {
+                write!(f, "{:02X}", b)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RData<'_> {
+    /// Writes the data in presentation format. Types without one, such as NULL and OPT, are
+    /// written in the generic format of RFC 3597.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RData::A(data) => write!(f, "{}", data.0),
+            RData::AAAA(data) => write!(f, "{}", data.0),
+            RData::PTR(data) => write!(f, "{}", Absolute(&data.0)),
+            RData::CNAME(data) => write!(f, "{}", Absolute(&data.0)),
+            RData::SRV(data) => write!(
+                f,
+                "{} {} {} {}",
+                data.priority,
+                data.weight,
+                data.port,
+                Absolute(&data.target)
+            ),
+            RData::TXT(data) => {
+                for (i, s) in data.0.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(" ")?;
+                    }
+                    write!(f, "{}", Quoted(s))?;
+                }
+                Ok(())
+            }
+            RData::HINFO(data) => write!(f, "{} {}", Quoted(&data.cpu), Quoted(&data.os)),
+            RData::NSEC(data) => {
+                write!(f, "{}", Absolute(&data.next_domain))?;
+                for rtype in &data.types {
+                    write!(f, " {}", type_name(*rtype))?;
+                }
+                Ok(())
+            }
+            RData::NULL(data) => write!(f, "{}", Generic(&data.0)),
+            RData::OPT(data) => {
+                let data = to_vec(data).map_err(|_| fmt::Error)?;
+                write!(f, "{}", Generic(&data))
+            }
+            RData::Unknown { data, .. } => write!(f, "{}", Generic(data)),
+        }
+    }
+}
+
+impl fmt::Display for ResourceRecord<'_> {
+    /// Writes the record on one line, as printed by `dig`:
+    /// `marin._myservice._tcp.local. 4500 IN SRV 0 0 8594 marin.local.`
+    ///
+    /// The class of records with the cache-flush bit set is written as `CLASS32769`. The class and
+    /// ttl of an OPT pseudo-record are its raw fields.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (ttl, class) = match &self.data {
+            RData::OPT(opt) => (opt.ttl(), Mnemonic(&[], "CLASS", opt.udp_payload_size)),
+            _ => (
+                duration_to_secs(self.ttl),
+                class_name(self.class.code(), self.cache_flush),
+            ),
+        };
+        write!(
+            f,
+            "{} {} {} {} {}",
+            Absolute(&self.name),
+            ttl,
+            class,
+            type_name(self.data.code()),
+            self.data
+        )
+    }
+}
+
+/// Writes an entry of an UPDATE message that is not a plain record, with a ttl of 0.
+fn write_entry(
+    f: &mut fmt::Formatter<'_>,
+    name: &Name,
+    class: u16,
+    rtype: u16,
+    data: Option<&RData>,
+) -> fmt::Result {
+    write!(
+        f,
+        "{} 0 {} {}",
+        Absolute(name),
+        class_name(class, false),
+        type_name(rtype)
+    )?;
+    match data {
+        Some(data) => write!(f, " {}", data),
+        None => Ok(()),
+    }
+}
+
+impl fmt::Display for Prerequisite<'_> {
+    /// Writes the prerequisite as the record that carries it, as printed by `dig`:
+    /// `marin.local. 0 ANY A`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Prerequisite::Exists { name, rtype } => write_entry(f, name, ANY, *rtype, None),
+            Prerequisite::ExistsWith(record) => write_entry(
+                f,
+                &record.name,
+                record.class.code(),
+                record.data.code(),
+                Some(&record.data),
+            ),
+            Prerequisite::NotExists { name, rtype } => write_entry(f, name, NONE, *rtype, None),
+            Prerequisite::NameInUse(name) => write_entry(f, name, ANY, ANY, None),
+            Prerequisite::NameNotInUse(name) => write_entry(f, name, NONE, ANY, None),
+        }
+    }
+}
+
+impl fmt::Display for Update<'_> {
+    /// Writes the update as the record that carries it, as printed by `dig`:
+    /// `marin.local. 0 NONE A 192.168.1.7`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Update::Add(record) => write!(f, "{}", record),
+            Update::DeleteRRset { name, rtype } => write_entry(f, name, ANY, *rtype, None),
+            Update::DeleteName(name) => write_entry(f, name, ANY, ANY, None),
+            Update::Delete { name, data } => write_entry(f, name, NONE, data.code(), Some(data)),
+        }
+    }
+}
+
+impl fmt::Display for Question<'_> {
+    /// Writes the question as `name. class type`. The class of questions preferring a unicast
+    /// response is written as `CLASS32769`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} {} {}",
+            Absolute(&self.name),
+            class_name(self.qclass as u16, self.prefer_unicast),
+            type_name(self.qtype.code())
+        )
+    }
+}
+
+impl fmt::Display for Message {
+    /// Writes the message as printed by `dig`: the header, the EDNS0 pseudo-section, then every
+    /// non-empty section. The sections of an UPDATE message are named after their content.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.header())?;
+
+        if let Some(opt) = self.opt() {
+            write!(
+                f,
+                "\n\n;; OPT PSEUDOSECTION:\n; EDNS: version: {}, flags:",
+                opt.version
+            )?;
+            if opt.dnssec_ok {
+                write!(f, " do")?;
+            }
+            write!(f, "; udp: {}", opt.udp_payload_size)?;
+        }
+
+        if !self.questions().is_empty() {
+            let title = match self.header().opcode() {
+                OpCode::UPDATE => "ZONE",
+                _ => "QUESTION",
+            };
+            write!(f, "\n\n;; {} SECTION:", title)?;
+            for question in self.questions() {
+                write!(f, "\n;{}", question)?;
+            }
+        }
+        if !self.prerequisites().is_empty() {
+            write!(f, "\n\n;; PREREQUISITE SECTION:")?;
+            for prerequisite in self.prerequisites() {
+                write!(f, "\n{}", prerequisite)?;
+            }
+        }
+        if !self.updates().is_empty() {
+            write!(f, "\n\n;; UPDATE SECTION:")?;
+            for update in self.updates() {
+                write!(f, "\n{}", update)?;
+            }
+        }
+
+        let sections = [
+            ("ANSWER", self.answers()),
+            ("AUTHORITY", self.authorities()),
+            ("ADDITIONAL", self.additionals()),
+        ];
+        for (title, records) in sections.iter() {
+            let mut records = records
+                .iter()
+                .filter(|record| !matches!(record.data(), RData::OPT(_)))
+                .peekable();
+            if records.peek().is_none() {
+                continue;
+            }
+            write!(f, "\n\n;; {} SECTION:", title)?;
+            for record in records {
+                write!(f, "\n{}", record)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A field of a line in presentation format.
+pub(crate) struct Token {
+    /// The field, with its escape sequences if it is not quoted, or unescaped if it is.
+    pub text: String,
+    pub quoted: bool,
+}
+
+impl Token {
+    /// Returns whether the token is the given unquoted word, such as `(`.
+    pub fn is(&self, word: &str) -> bool {
+        !self.quoted && self.text == word
+    }
+
+    /// Returns the token as a character string, with its escape sequences decoded.
+    pub fn character_string(&self) -> Result<String, ParseError> {
+        match self.quoted {
+            true => Ok(self.text.clone()),
+            false => unescape(&self.text),
+        }
+    }
+}
+
+/// Splits a line into fields, separated by whitespace. A field may be a quoted string, and a `;`
+/// outside of a quoted string starts a comment. Parentheses are fields of their own.
+pub(crate) fn tokenize(line: &str) -> Result<Vec<Token>, ParseError> {
+    let mut tokens = Vec::new();
+    let mut chars = line.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c == ';' {
+            break;
+        } else if c == '(' || c == ')' {
+            chars.next();
+            tokens.push(Token {
+                text: c.to_string(),
+                quoted: false,
+            });
+        } else if c == '"' {
+            chars.next();
+            let mut text = String::new();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => {
+                        text.push('\\');
+                        text.push(chars.next().ok_or(ParseError::UnterminatedString)?);
+                    }
+                    Some(c) => text.push(c),
+                    None => return Err(ParseError::UnterminatedString),
+                }
+            }
+            let text = unescape(&text)?;
+            tokens.push(Token { text, quoted: true });
+        } else {
+            let mut text = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_whitespace() || matches!(c, ';' | '"' | '(' | ')') {
+                    break;
+                }
+                chars.next();
+                text.push(c);
+                if c == '\\' {
+                    text.extend(chars.next());
+                }
+            }
+            tokens.push(Token {
+                text,
+                quoted: false,
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+/// Decodes the `\X` and `\DDD` escape sequences of a character string. `\DDD` escapes are octets,
+/// so the string is decoded once it is complete.
+fn unescape(s: &str) -> Result<String, ParseError> {
+    let invalid = || ParseError::InvalidRData(s.to_string());
+    let mut text = Vec::new();
+    let push =
+        |text: &mut Vec<u8>, c: char| text.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            push(&mut text, c);
+            continue;
+        }
+        let escaped = chars.next().ok_or_else(invalid)?;
+        if escaped.is_ascii_digit() {
+            let digits: String = core::iter::once(escaped)
+                .chain((0..2).filter_map(|_| chars.next()))
+                .collect();
+            let byte = digits
+                .parse::<u8>()
+                .map_err(|_| ParseError::InvalidRData(digits))?;
+            text.push(byte);
+        } else {
+            push(&mut text, escaped);
+        }
+    }
+    String::from_utf8(text).map_err(|error| {
+        ParseError::InvalidRData(String::from_utf8_lossy(error.as_bytes()).into_owned())
+    })
+}
+
+impl FromStr for ResourceRecord<'static> {
+    type Err = ParseError;
+
+    /// Parses a record written on one line as `name ttl class type rdata`, as written by its
+    /// `Display` implementation. Data of any type can also be given in the generic format of
+    /// RFC 3597 §5.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        parse_record(&tokenize(s)?)
+    }
+}
+
+/// Parses records written in presentation format, one per line. Empty lines and comments are
+/// ignored, so the output of `dig` can be used as is.
+pub fn parse_records(s: &str) -> Result<Vec<ResourceRecord<'static>>, ParseError> {
+    s.lines()
+        .map(tokenize)
+        .filter(|tokens| !matches!(tokens, Ok(tokens) if tokens.is_empty()))
+        .map(|tokens| parse_record(&tokens?))
+        .collect()
+}
+
+fn parse_record(tokens: &[Token]) -> Result<ResourceRecord<'static>, ParseError> {
+    let mut fields = tokens.iter();
+    let mut next = |field| fields.next().ok_or(ParseError::MissingField(field));
+
+    let name = next("name")?.text.parse::<Name>()?;
+    let ttl = next("ttl")?;
+    let ttl = ttl
+        .text
+        .parse::<u32>()
+        .map_err(|_| ParseError::InvalidTtl(ttl.text.clone()))?;
+    let class = next("class")?;
+    let raw_class = parse_mnemonic(CLASSES, "CLASS", &class.text)
+        .ok_or_else(|| ParseError::InvalidClass(class.text.clone()))?;
+    let rtype = next("type")?;
+    let rtype = parse_mnemonic(TYPES, "TYPE", &rtype.text)
+        .ok_or_else(|| ParseError::InvalidType(rtype.text.clone()))?;
+    let rdata = &tokens[4..];
+
+    if rtype as usize == Opt::TYPE {
+        let data = parse_generic(rdata)?;
+        let opt = Opt::read(raw_class, ttl, &mut Reader::new(&data))
+            .map_err(|_| ParseError::InvalidRData(join(rdata)))?;
+        return Ok(ResourceRecord::opt(opt.into_owned()));
+    }
+
+    let class = Class::parse(raw_class & 0x7fff);
+    let mut record = ResourceRecord::new(
+        name,
+        Duration::from_secs(ttl as u64),
+        class,
+        parse_rdata(rtype, rdata, &|s| s.parse())?,
+    );
+    record.cache_flush = raw_class & 0x8000 != 0;
+    Ok(record)
+}
+
+pub(crate) fn join(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| token.text.as_str())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Parses data in the generic format of RFC 3597 §5: `\# length hex`, where the hexadecimal data
+/// may be split into several fields.
+fn parse_generic(tokens: &[Token]) -> Result<Vec<u8>, ParseError> {
+    let invalid = || ParseError::InvalidRData(join(tokens));
+    match tokens.first() {
+        Some(token) if token.is("\\#") => (),
+        _ => return Err(invalid()),
+    }
+    let len = tokens
+        .get(1)
+        .and_then(|token| token.text.parse::<usize>().ok())
+        .ok_or_else(invalid)?;
+
+    let hex: String = tokens[2..]
+        .iter()
+        .map(|token| token.text.as_str())
+        .collect();
+    if hex.len() != len * 2 || !hex.is_ascii() {
+        return Err(invalid());
+    }
+    (0..len)
+        .map(|i| u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).map_err(|_| invalid()))
+        .collect()
+}
+
+/// Parses the data of a record of type `rtype`. Names are built by `name`, which lets master files
+/// resolve relative names.
+pub(crate) fn parse_rdata(
+    rtype: u16,
+    tokens: &[Token],
+    name: &dyn Fn(&str) -> Result<Name<'static>, ParseError>,
+) -> Result<RData<'static>, ParseError> {
+    let invalid = || ParseError::InvalidRData(join(tokens));
+
+    if matches!(tokens.first(), Some(token) if token.is("\\#")) {
+        let data = parse_generic(tokens)?;
+        let mut reader = Reader::new(&data);
+        let rdata = RData::read(rtype, &mut reader).map_err(|_| invalid())?;
+        if !reader.is_empty() {
+            return Err(invalid());
+        }
+        return Ok(rdata.into_owned());
+    }
+
+    let texts: Vec<&str> = tokens.iter().map(|token| token.text.as_str()).collect();
+    let number = |s: &str| s.parse::<u16>().map_err(|_| invalid());
+    let rdata = match (TYPES.iter().find(|(code, _)| *code == rtype), &texts[..]) {
+        (Some((_, "A")), [addr]) => RData::a(addr.parse().map_err(|_| invalid())?),
+        (Some((_, "AAAA")), [addr]) => RData::aaaa(addr.parse().map_err(|_| invalid())?),
+        (Some((_, "PTR")), [ptr]) => RData::ptr(name(ptr)?),
+        (Some((_, "CNAME")), [cname]) => RData::cname(name(cname)?),
+        (Some((_, "SRV")), [priority, weight, port, target]) => RData::srv(
+            number(port)?,
+            number(priority)?,
+            number(weight)?,
+            name(target)?,
+        ),
+        (Some((_, "TXT")), _) => RData::txt_strings(
+            tokens
+                .iter()
+                .map(Token::character_string)
+                .collect::<Result<Vec<_>, _>>()?,
+        ),
+        (Some((_, "HINFO")), [_, _]) => {
+            RData::hinfo(tokens[0].character_string()?, tokens[1].character_string()?)
+        }
+        (Some((_, "NSEC")), [next_domain, types @ ..]) => {
+            let types = types
+                .iter()
+                .map(|rtype| parse_mnemonic(TYPES, "TYPE", rtype).ok_or_else(invalid))
+                .collect::<Result<Vec<_>, _>>()?;
+            RData::nsec(name(next_domain)?, types)
+        }
+        _ => return Err(invalid()),
+    };
+    Ok(rdata)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dns::PacketBuilder;
+    use core::net::Ipv4Addr;
+
+    #[test]
+    fn record_round_trip() {
+        let lines = [
+            "marin._myservice._tcp.local. 4500 IN SRV 0 0 8594 marin.local.",
+            "marin.local. 120 CLASS32769 A 192.168.1.7",
+            "marin.local. 120 IN AAAA fe80::1",
+            "_myservice._tcp.local. 4500 IN PTR Marin's\\032Mac\\.2._myservice._tcp.local.",
+            r#"marin._myservice._tcp.local. 4500 IN TXT "path=/" "say \"hi\"" "back\\slash""#,
+            r#"marin.local. 4500 IN HINFO "ARM" "Linux""#,
+            "marin.local. 4500 IN NSEC marin.local. A AAAA TYPE99",
+            "alias.local. 4500 IN CNAME marin.local.",
+            "marin.local. 4500 IN NULL \\# 3 010203",
+            "marin.local. 4500 IN TYPE99 \\# 2 CAFE",
+            ". 0 CLASS1440 OPT \\# 0",
+        ];
+        for line in lines.iter() {
+            let record: ResourceRecord = line.parse().unwrap();
+            assert_eq!(&record.to_string(), line);
+        }
+
+        let record: ResourceRecord = lines[0].parse().unwrap();
+        assert_eq!(
+            record,
+            ResourceRecord::IN(
+                "marin._myservice._tcp.local",
+                RData::srv(8594, 0, 0, "marin.local")
+            )
+        );
+        let record: ResourceRecord = lines[1].parse().unwrap();
+        assert!(record.is_unique());
+        assert_eq!(record.data(), &RData::a(Ipv4Addr::new(192, 168, 1, 7)));
+    }
+
+    #[test]
+    fn parse_unquoted_escapes() {
+        let record: ResourceRecord =
+            r#"marin.local. 120 IN TXT a\032b "c\032d" \"e\;"#.parse().unwrap();
+        assert_eq!(record.data(), &RData::txt(&["a b", "c d", "\"e;"]));
+
+        let record: ResourceRecord =
+            r#"marin.local. 120 IN HINFO ARM\03264 Linux"#.parse().unwrap();
+        assert_eq!(record.data(), &RData::hinfo("ARM 64", "Linux"));
+
+        assert_eq!(
+            r#"marin.local. 120 IN TXT a\999"#.parse::<ResourceRecord>().unwrap_err(),
+            ParseError::InvalidRData("999".to_string())
+        );
+    }
+
+    #[test]
+    fn parse_generic_rdata() {
+        let record: ResourceRecord = "marin.local. 120 IN A \\# 4 C0A8 0107".parse().unwrap();
+        assert_eq!(record.data(), &RData::a(Ipv4Addr::new(192, 168, 1, 7)));
+    }
+
+    #[test]
+    fn parse_invalid_records() {
+        let errors = [
+            ("marin.local. 120 IN", ParseError::MissingField("type")),
+            (
+                "marin.local. ten IN A 192.168.1.7",
+                ParseError::InvalidTtl("ten".to_string()),
+            ),
+            (
+                "marin.local. 120 XX A 192.168.1.7",
+                ParseError::InvalidClass("XX".to_string()),
+            ),
+            (
+                "marin.local. 120 IN FOO 192.168.1.7",
+                ParseError::InvalidType("FOO".to_string()),
+            ),
+            (
+                "marin.local. 120 IN A 192.168.1",
+                ParseError::InvalidRData("192.168.1".to_string()),
+            ),
+            (
+                "marin.local. 120 IN A \\# 4 C0A801",
+                ParseError::InvalidRData("\\# 4 C0A801".to_string()),
+            ),
+            (
+                r#"marin.local. 120 IN TXT "path=/"#,
+                ParseError::UnterminatedString,
+            ),
+        ];
+        for (line, error) in errors.iter() {
+            assert_eq!(&line.parse::<ResourceRecord>().unwrap_err(), error);
+        }
+    }
+
+    #[test]
+    fn parse_dig_output() {
+        let records = parse_records(
+            ";; ANSWER SECTION:
+            _myservice._tcp.local. 4500 IN PTR marin._myservice._tcp.local. ; shared
+
+            ;; ADDITIONAL SECTION:
+            marin._myservice._tcp.local. 4500 IN SRV 0 0 8594 marin.local.",
+        )
+        .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[1].data(), &RData::srv(8594, 0, 0, "marin.local"));
+    }
+
+    #[test]
+    fn display_message() {
+        let mut packet = PacketBuilder::new();
+        packet.header_mut().set_id(12).set_query(false);
+        packet.add_answer(ResourceRecord::IN(
+            "_myservice._tcp.local",
+            RData::ptr("marin._myservice._tcp.local"),
+        ));
+        packet.add_additional(
+            ResourceRecord::IN("marin.local", RData::a(Ipv4Addr::new(192, 168, 1, 7)))
+                .set_ttl(Duration::from_secs(120))
+                .unique(),
+        );
+        packet.set_opt(Opt::new(1440));
+        let message = Message::parse(&packet.build()).unwrap();
+
+        assert_eq!(
+            message.to_string(),
+            ";; ->>HEADER<<- opcode: QUERY, status: NOERROR, id: 12
+;; flags: qr; QUERY: 0, ANSWER: 1, AUTHORITY: 0, ADDITIONAL: 2
+
+;; OPT PSEUDOSECTION:
+; EDNS: version: 0, flags:; udp: 1440
+
+;; ANSWER SECTION:
+_myservice._tcp.local. 4500 IN PTR marin._myservice._tcp.local.
+
+;; ADDITIONAL SECTION:
+marin.local. 120 CLASS32769 A 192.168.1.7"
+        );
+    }
+}
@@ -1,8 +1,12 @@
 use super::super::append_u32;
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::{ReadBytes, Reader};
 use super::super::traits::AppendBytes;
-use std::net::Ipv4Addr;
+use super::super::writer::Writer;
+use core::net::Ipv4Addr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Record(pub Ipv4Addr);
 
 impl Record {
@@ -10,7 +14,14 @@ impl Record {
 }
 
 impl AppendBytes for Record {
-    fn append_bytes(&self, out: &mut Vec<u8>) {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
         append_u32(out, self.0.into());
+        Ok(())
+    }
+}
+
+impl ReadBytes<'_> for Record {
+    fn read_bytes(reader: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(Record(reader.read_u32()?.into()))
     }
 }
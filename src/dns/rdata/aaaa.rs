@@ -1,7 +1,12 @@
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::{ReadBytes, Reader};
 use super::super::traits::AppendBytes;
-use std::net::Ipv6Addr;
+use super::super::writer::Writer;
+use core::convert::TryFrom;
+use core::net::Ipv6Addr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Record(pub Ipv6Addr);
 
 impl Record {
@@ -9,9 +14,15 @@ impl Record {
 }
 
 impl AppendBytes for Record {
-    fn append_bytes(&self, out: &mut Vec<u8>) {
-        for b in u128::to_be_bytes(self.0.into()).iter() {
-            out.push(*b);
-        }
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        out.extend_from_slice(&self.0.octets());
+        Ok(())
+    }
+}
+
+impl ReadBytes<'_> for Record {
+    fn read_bytes(reader: &mut Reader) -> Result<Self, DecodeError> {
+        let octets = <[u8; 16]>::try_from(reader.read_slice(16)?).unwrap();
+        Ok(Record(octets.into()))
     }
 }
@@ -0,0 +1,36 @@
+use super::super::append_qname;
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::{ReadBytes, Reader};
+use super::super::traits::AppendBytes;
+use super::super::writer::Writer;
+use super::super::Name;
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record<'a>(pub Name<'a>);
+
+impl<'a> Record<'a> {
+    pub const TYPE: usize = 5;
+
+    /// Converts the record into a record that owns its data.
+    pub fn into_owned(self) -> Record<'static> {
+        Record(self.0.into_owned())
+    }
+
+    /// Returns a record borrowing its data from `self`.
+    pub fn borrowed(&self) -> Record<'_> {
+        Record(self.0.borrowed())
+    }
+}
+
+impl AppendBytes for Record<'_> {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        append_qname(out, &self.0)
+    }
+}
+
+impl<'a> ReadBytes<'a> for Record<'a> {
+    fn read_bytes(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        Ok(Record(reader.read_name()?))
+    }
+}
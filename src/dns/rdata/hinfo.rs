@@ -0,0 +1,53 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+
+use super::super::append_character_string;
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::{ReadBytes, Reader};
+use super::super::traits::AppendBytes;
+use super::super::writer::Writer;
+
+/// Host information, such as the device model that Apple devices publish.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record<'a> {
+    pub cpu: Cow<'a, str>,
+    pub os: Cow<'a, str>,
+}
+
+impl<'a> Record<'a> {
+    pub const TYPE: usize = 13;
+
+    /// Converts the record into a record that owns its data.
+    pub fn into_owned(self) -> Record<'static> {
+        Record {
+            cpu: Cow::Owned(self.cpu.into_owned()),
+            os: Cow::Owned(self.os.into_owned()),
+        }
+    }
+
+    /// Returns a record borrowing its data from `self`.
+    pub fn borrowed(&self) -> Record<'_> {
+        Record {
+            cpu: Cow::Borrowed(&self.cpu),
+            os: Cow::Borrowed(&self.os),
+        }
+    }
+}
+
+impl AppendBytes for Record<'_> {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        append_character_string(out, self.cpu.as_bytes())?;
+        append_character_string(out, self.os.as_bytes())
+    }
+}
+
+impl<'a> ReadBytes<'a> for Record<'a> {
+    /// Strings that are not valid UTF-8 are decoded lossily.
+    fn read_bytes(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        Ok(Record {
+            cpu: String::from_utf8_lossy(reader.read_character_string()?),
+            os: String::from_utf8_lossy(reader.read_character_string()?),
+        })
+    }
+}
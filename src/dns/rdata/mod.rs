@@ -1,5 +1,10 @@
 pub mod a;
 pub mod aaaa;
+pub mod cname;
+pub mod hinfo;
+pub mod nsec;
+pub mod null;
+pub mod opt;
 pub mod ptr;
 pub mod srv;
 pub mod txt;
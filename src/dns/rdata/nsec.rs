@@ -0,0 +1,139 @@
+use super::super::append_qname;
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::{ReadBytes, Reader};
+use super::super::traits::AppendBytes;
+use super::super::writer::Writer;
+use super::super::Name;
+use alloc::vec::Vec;
+
+/// A NSEC record (RFC 4034 §4).
+///
+/// mDNS uses a restricted form of NSEC records to assert that a name has no record of a given
+/// type (RFC 6762 §6.1): `next_domain` is then the name of the record itself, and `types` only
+/// contains types lower than 256.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record<'a> {
+    pub next_domain: Name<'a>,
+    /// Types of the records that exist for the name, in ascending order.
+    pub types: Vec<u16>,
+}
+
+impl<'a> Record<'a> {
+    pub const TYPE: usize = 47;
+
+    /// Converts the record into a record that owns its data.
+    pub fn into_owned(self) -> Record<'static> {
+        Record {
+            next_domain: self.next_domain.into_owned(),
+            types: self.types,
+        }
+    }
+
+    /// Returns a record borrowing its data from `self`.
+    pub fn borrowed(&self) -> Record<'_> {
+        Record {
+            next_domain: self.next_domain.borrowed(),
+            types: self.types.clone(),
+        }
+    }
+
+    /// Returns whether the type bitmap contains `rtype`.
+    pub fn contains(&self, rtype: u16) -> bool {
+        self.types.contains(&rtype)
+    }
+}
+
+impl AppendBytes for Record<'_> {
+    /// Writes the next domain name followed by the type bitmap. The bitmap is split into windows
+    /// of 256 types, each of them written with the length of its last non-zero byte.
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        append_qname(out, &self.next_domain)?;
+
+        // windows are written in ascending order, whatever the order of `types`
+        let mut window = self.types.iter().map(|&rtype| rtype >> 8).min();
+        while let Some(current) = window {
+            let mut bitmap = [0u8; 32];
+            let mut len = 0;
+            for &rtype in self.types.iter().filter(|&&rtype| rtype >> 8 == current) {
+                let low = (rtype & 0xff) as usize;
+                bitmap[low / 8] |= 0x80 >> (low % 8);
+                len = len.max(low / 8 + 1);
+            }
+            out.push(current as u8);
+            out.push(len as u8);
+            out.extend_from_slice(&bitmap[..len]);
+
+            window = self
+                .types
+                .iter()
+                .map(|&rtype| rtype >> 8)
+                .filter(|&window| window > current)
+                .min();
+        }
+
+        Ok(())
+    }
+}
+
+impl<'a> ReadBytes<'a> for Record<'a> {
+    fn read_bytes(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let next_domain = reader.read_name()?;
+
+        let mut types = Vec::new();
+        while !reader.is_empty() {
+            let window = reader.read_u8()? as u16;
+            let len = reader.read_u8()? as usize;
+            if len == 0 || len > 32 {
+                return Err(DecodeError::InvalidRData);
+            }
+            for (i, byte) in reader.read_slice(len)?.iter().enumerate() {
+                for bit in 0..8 {
+                    if byte & (0x80 >> bit) != 0 {
+                        types.push(window << 8 | (i * 8 + bit) as u16);
+                    }
+                }
+            }
+        }
+
+        Ok(Record { next_domain, types })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::writer::to_vec;
+    use super::*;
+
+    #[test]
+    fn type_bitmap_round_trip() {
+        let record = Record {
+            next_domain: Name::from("marin.local"),
+            // SRV, A and a type in the second window
+            types: vec![33, 1, 257],
+        };
+        let buffer = to_vec(&record).unwrap();
+
+        #[rustfmt::skip]
+        assert_eq!(
+            &buffer[13..],
+            &[
+                0, 5, 0x40, 0, 0, 0, 0x40,
+                1, 1, 0x40,
+            ]
+        );
+
+        let read = Record::read_bytes(&mut Reader::new(&buffer)).unwrap();
+        assert_eq!(read.next_domain, "marin.local");
+        assert_eq!(read.types, vec![1, 33, 257]);
+    }
+
+    #[test]
+    fn reject_empty_window() {
+        let buffer = [0, 0, 0];
+        assert_eq!(
+            Record::read_bytes(&mut Reader::new(&buffer)).unwrap_err(),
+            DecodeError::InvalidRData
+        );
+    }
+}
@@ -0,0 +1,41 @@
+use alloc::borrow::Cow;
+
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::{ReadBytes, Reader};
+use super::super::traits::AppendBytes;
+use super::super::writer::Writer;
+
+/// A NULL record, whose data is opaque.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record<'a>(
+    #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::hex"))] pub Cow<'a, [u8]>,
+);
+
+impl<'a> Record<'a> {
+    pub const TYPE: usize = 10;
+
+    /// Converts the record into a record that owns its data.
+    pub fn into_owned(self) -> Record<'static> {
+        Record(Cow::Owned(self.0.into_owned()))
+    }
+
+    /// Returns a record borrowing its data from `self`.
+    pub fn borrowed(&self) -> Record<'_> {
+        Record(Cow::Borrowed(&self.0))
+    }
+}
+
+impl AppendBytes for Record<'_> {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        out.extend_from_slice(&self.0);
+        Ok(())
+    }
+}
+
+impl<'a> ReadBytes<'a> for Record<'a> {
+    fn read_bytes(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let data = reader.read_slice(reader.remaining())?;
+        Ok(Record(Cow::Borrowed(data)))
+    }
+}
@@ -0,0 +1,349 @@
+use alloc::borrow::Cow;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+
+use super::super::append_u16;
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::Reader;
+use super::super::traits::AppendBytes;
+use super::super::writer::Writer;
+
+/// An EDNS0 OPT pseudo-record (RFC 6891 §6.1).
+///
+/// The OPT record reuses the class and ttl fields of its resource record to carry the UDP payload
+/// size, the extended RCODE, the EDNS version and the DO bit. Its name is always the root.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record<'a> {
+    /// The largest UDP payload the sender can receive.
+    pub udp_payload_size: u16,
+    /// The upper 8 bits of the RCODE of the message.
+    pub extended_rcode: u8,
+    pub version: u8,
+    /// The `DO` bit, set when the sender supports DNSSEC.
+    pub dnssec_ok: bool,
+    pub options: Vec<EdnsOption<'a>>,
+}
+
+impl<'a> Record<'a> {
+    pub const TYPE: usize = 41;
+
+    /// Creates an OPT record advertising `udp_payload_size`, without any option.
+    pub fn new(udp_payload_size: u16) -> Self {
+        Self {
+            udp_payload_size,
+            extended_rcode: 0,
+            version: 0,
+            dnssec_ok: false,
+            options: Vec::new(),
+        }
+    }
+
+    /// Converts the record into a record that owns its data.
+    pub fn into_owned(self) -> Record<'static> {
+        Record {
+            udp_payload_size: self.udp_payload_size,
+            extended_rcode: self.extended_rcode,
+            version: self.version,
+            dnssec_ok: self.dnssec_ok,
+            options: self
+                .options
+                .into_iter()
+                .map(EdnsOption::into_owned)
+                .collect(),
+        }
+    }
+
+    /// Returns a record borrowing its data from `self`.
+    pub fn borrowed(&self) -> Record<'_> {
+        Record {
+            udp_payload_size: self.udp_payload_size,
+            extended_rcode: self.extended_rcode,
+            version: self.version,
+            dnssec_ok: self.dnssec_ok,
+            options: self.options.iter().map(EdnsOption::borrowed).collect(),
+        }
+    }
+
+    /// Returns the value of the ttl field of the record.
+    pub(crate) fn ttl(&self) -> u32 {
+        let dnssec_ok = if self.dnssec_ok { 1 << 15 } else { 0 };
+        (self.extended_rcode as u32) << 24 | (self.version as u32) << 16 | dnssec_ok
+    }
+
+    /// Reads the record from its rdata, and the class and ttl fields of its resource record.
+    pub(crate) fn read(class: u16, ttl: u32, reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let mut options = Vec::new();
+        while !reader.is_empty() {
+            let code = reader.read_u16()?;
+            let len = reader.read_u16()? as usize;
+            let data = reader.read_slice(len)?;
+            options.push(EdnsOption::read(code, data));
+        }
+
+        Ok(Record {
+            udp_payload_size: class,
+            extended_rcode: (ttl >> 24) as u8,
+            version: (ttl >> 16) as u8,
+            dnssec_ok: ttl & 1 << 15 != 0,
+            options,
+        })
+    }
+}
+
+impl AppendBytes for Record<'_> {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        for option in &self.options {
+            option.append_bytes(out)?;
+        }
+        Ok(())
+    }
+}
+
+/// An EDNS option, carried in the rdata of an OPT record.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EdnsOption<'a> {
+    Owner(Owner),
+    /// An option that is not supported, kept in its wire format.
+    Other {
+        code: u16,
+        #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::hex"))]
+        data: Cow<'a, [u8]>,
+    },
+}
+
+impl<'a> EdnsOption<'a> {
+    pub fn code(&self) -> u16 {
+        match self {
+            EdnsOption::Owner(_) => Owner::CODE,
+            EdnsOption::Other { code, .. } => *code,
+        }
+    }
+
+    pub fn into_owned(self) -> EdnsOption<'static> {
+        match self {
+            EdnsOption::Owner(owner) => EdnsOption::Owner(owner),
+            EdnsOption::Other { code, data } => EdnsOption::Other {
+                code,
+                data: Cow::Owned(data.into_owned()),
+            },
+        }
+    }
+
+    pub fn borrowed(&self) -> EdnsOption<'_> {
+        match self {
+            EdnsOption::Owner(owner) => EdnsOption::Owner(owner.clone()),
+            EdnsOption::Other { code, data } => EdnsOption::Other {
+                code: *code,
+                data: Cow::Borrowed(data),
+            },
+        }
+    }
+
+    /// Reads an option from its code and data. Options that are not supported, or that are
+    /// malformed, are kept as `Other`.
+    fn read(code: u16, data: &'a [u8]) -> Self {
+        let option = match code {
+            Owner::CODE => Owner::read(data).map(EdnsOption::Owner),
+            _ => None,
+        };
+        option.unwrap_or(EdnsOption::Other {
+            code,
+            data: Cow::Borrowed(data),
+        })
+    }
+}
+
+impl AppendBytes for EdnsOption<'_> {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        let data = match self {
+            EdnsOption::Owner(owner) => Cow::Owned(owner.to_bytes()?),
+            EdnsOption::Other { data, .. } => Cow::Borrowed(&**data),
+        };
+        if data.len() > u16::MAX as usize {
+            return Err(EncodeError::RDataTooLong);
+        }
+        append_u16(out, self.code());
+        append_u16(out, data.len() as u16);
+        out.extend_from_slice(&data);
+        Ok(())
+    }
+}
+
+/// The EDNS0 Owner option, used by Bonjour sleep proxies to identify the device that owns the
+/// records of a message.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Owner {
+    pub version: u8,
+    pub sequence: u8,
+    #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::mac"))]
+    pub primary_mac: [u8; 6],
+    /// The MAC address to use to wake the device, if it's not `primary_mac`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::dns::serialization::mac_option")
+    )]
+    pub wakeup_mac: Option<[u8; 6]>,
+    /// The Wake-on-LAN password, either 4 or 6 bytes long.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::dns::serialization::hex_option")
+    )]
+    pub password: Option<Vec<u8>>,
+}
+
+impl Owner {
+    pub const CODE: u16 = 4;
+
+    fn to_bytes(&self) -> Result<Vec<u8>, EncodeError> {
+        let mut bytes = vec![self.version, self.sequence];
+        bytes.extend_from_slice(&self.primary_mac);
+        if self.wakeup_mac.is_some() || self.password.is_some() {
+            bytes.extend_from_slice(&self.wakeup_mac.unwrap_or(self.primary_mac));
+        }
+        if let Some(password) = &self.password {
+            if !matches!(password.len(), 4 | 6) {
+                return Err(EncodeError::InvalidPassword);
+            }
+            bytes.extend_from_slice(password);
+        }
+        Ok(bytes)
+    }
+
+    /// Reads the option from its data. A wakeup MAC address that is the primary one, which is
+    /// written when there is a password, is read as `None`.
+    fn read(data: &[u8]) -> Option<Self> {
+        let mac = |offset: usize| <[u8; 6]>::try_from(&data[offset..offset + 6]).ok();
+        let (wakeup_mac, password) = match data.len() {
+            8 => (None, None),
+            14 => (mac(8), None),
+            18 | 20 => (mac(8), Some(data[14..].to_vec())),
+            _ => return None,
+        };
+        let primary_mac = mac(2)?;
+        Some(Owner {
+            version: data[0],
+            sequence: data[1],
+            primary_mac,
+            wakeup_mac: wakeup_mac.filter(|&mac| mac != primary_mac),
+            password,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::super::writer::to_vec;
+    use super::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    #[test]
+    fn opt_round_trip() {
+        let record = Record {
+            udp_payload_size: 1440,
+            extended_rcode: 1,
+            version: 0,
+            dnssec_ok: true,
+            options: vec![
+                EdnsOption::Owner(Owner {
+                    version: 0,
+                    sequence: 3,
+                    primary_mac: [0x00, 0x11, 0x22, 0x33, 0x44, 0x55],
+                    wakeup_mac: None,
+                    password: None,
+                }),
+                EdnsOption::Other {
+                    code: 12,
+                    data: Cow::Borrowed(&[0, 0]),
+                },
+            ],
+        };
+        let buffer = to_vec(&record).unwrap();
+        assert_eq!(buffer.len(), 4 + 8 + 4 + 2);
+
+        let read = Record::read(1440, record.ttl(), &mut Reader::new(&buffer)).unwrap();
+        assert_eq!(read, record);
+    }
+
+    #[test]
+    fn reject_invalid_owner_password() {
+        let mut owner = Owner {
+            version: 0,
+            sequence: 3,
+            primary_mac: [0x00, 0x11, 0x22, 0x33, 0x44, 0x55],
+            wakeup_mac: None,
+            password: Some(vec![1, 2, 3, 4, 5]),
+        };
+        assert_eq!(
+            to_vec(&EdnsOption::Owner(owner.clone())),
+            Err(EncodeError::InvalidPassword)
+        );
+
+        owner.wakeup_mac = Some([0x00, 0x11, 0x22, 0x33, 0x44, 0x66]);
+        owner.password = Some(vec![1, 2, 3, 4, 5, 6]);
+        let buffer = to_vec(&EdnsOption::Owner(owner.clone())).unwrap();
+        assert_eq!(
+            EdnsOption::read(Owner::CODE, &buffer[4..]),
+            EdnsOption::Owner(owner)
+        );
+    }
+
+    #[test]
+    fn owner_password_without_wakeup_mac() {
+        // the primary MAC address is written in place of the wakeup one
+        let owner = Owner {
+            version: 0,
+            sequence: 3,
+            primary_mac: [0x00, 0x11, 0x22, 0x33, 0x44, 0x55],
+            wakeup_mac: None,
+            password: Some(vec![1, 2, 3, 4]),
+        };
+        let buffer = to_vec(&EdnsOption::Owner(owner.clone())).unwrap();
+        assert_eq!(&buffer[4 + 8..4 + 14], &owner.primary_mac);
+        assert_eq!(
+            EdnsOption::read(Owner::CODE, &buffer[4..]),
+            EdnsOption::Owner(owner)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn owner_round_trip(
+            version in any::<u8>(),
+            sequence in any::<u8>(),
+            primary_mac in any::<[u8; 6]>(),
+            wakeup_mac in any::<Option<[u8; 6]>>(),
+            password in proptest::option::of(
+                prop_oneof![vec(any::<u8>(), 4), vec(any::<u8>(), 6)],
+            ),
+        ) {
+            let owner = Owner {
+                version,
+                sequence,
+                primary_mac,
+                wakeup_mac: wakeup_mac.filter(|&mac| mac != primary_mac),
+                password,
+            };
+            let buffer = to_vec(&EdnsOption::Owner(owner.clone())).unwrap();
+            prop_assert_eq!(
+                EdnsOption::read(Owner::CODE, &buffer[4..]),
+                EdnsOption::Owner(owner)
+            );
+        }
+    }
+
+    #[test]
+    fn malformed_owner_option() {
+        assert_eq!(
+            EdnsOption::read(Owner::CODE, &[0, 1, 2]),
+            EdnsOption::Other {
+                code: Owner::CODE,
+                data: Cow::Owned(vec![0, 1, 2]),
+            }
+        );
+    }
+}
@@ -1,23 +1,56 @@
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::{ReadBytes, Reader};
 use super::super::traits::AppendBytes;
-use super::super::{append_qname, append_u16};
+use super::super::writer::Writer;
+use super::super::{append_qname, append_u16, Name};
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Record<'a> {
     pub port: u16,
     pub weight: u16,
     pub priority: u16,
-    pub target: &'a str,
+    pub target: Name<'a>,
 }
 
 impl<'a> Record<'a> {
     pub const TYPE: usize = 33;
+
+    /// Converts the record into a record that owns its data.
+    pub fn into_owned(self) -> Record<'static> {
+        Record {
+            target: self.target.into_owned(),
+            ..self
+        }
+    }
+
+    /// Returns a record borrowing its data from `self`.
+    pub fn borrowed(&self) -> Record<'_> {
+        Record {
+            port: self.port,
+            weight: self.weight,
+            priority: self.priority,
+            target: self.target.borrowed(),
+        }
+    }
 }
 
 impl AppendBytes for Record<'_> {
-    fn append_bytes(&self, out: &mut Vec<u8>) {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
         append_u16(out, self.priority);
         append_u16(out, self.weight);
         append_u16(out, self.port);
-        append_qname(out, self.target.as_bytes());
+        append_qname(out, &self.target)
+    }
+}
+
+impl<'a> ReadBytes<'a> for Record<'a> {
+    fn read_bytes(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        Ok(Record {
+            priority: reader.read_u16()?,
+            weight: reader.read_u16()?,
+            port: reader.read_u16()?,
+            target: reader.read_name()?,
+        })
     }
 }
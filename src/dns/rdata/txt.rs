@@ -1,17 +1,61 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::iter::FromIterator;
+
+use super::super::append_character_string;
+use super::super::error::{DecodeError, EncodeError};
+use super::super::reader::{ReadBytes, Reader};
 use super::super::traits::AppendBytes;
+use super::super::writer::Writer;
 
-#[derive(Debug)]
-pub struct Record<'a>(pub &'a [&'a str]);
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record<'a>(pub Vec<Cow<'a, str>>);
 
 impl<'a> Record<'a> {
     pub const TYPE: usize = 16;
+
+    /// Converts the record into a record that owns its data.
+    pub fn into_owned(self) -> Record<'static> {
+        Record(
+            self.0
+                .into_iter()
+                .map(|s| Cow::Owned(s.into_owned()))
+                .collect(),
+        )
+    }
+
+    /// Returns a record borrowing its data from `self`.
+    pub fn borrowed(&self) -> Record<'_> {
+        Record(self.0.iter().map(|s| Cow::Borrowed(&**s)).collect())
+    }
+}
+
+impl<'a, S: Into<Cow<'a, str>>> FromIterator<S> for Record<'a> {
+    fn from_iter<I: IntoIterator<Item = S>>(iter: I) -> Self {
+        Record(iter.into_iter().map(Into::into).collect())
+    }
 }
 
 impl AppendBytes for Record<'_> {
-    fn append_bytes(&self, out: &mut Vec<u8>) {
-        for s in self.0 {
-            out.push(s.len() as u8);
-            out.extend_from_slice(s.as_bytes());
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        for s in &self.0 {
+            append_character_string(out, s.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ReadBytes<'a> for Record<'a> {
+    /// Reads the character strings of the record. Strings that are not valid UTF-8 are decoded
+    /// lossily.
+    fn read_bytes(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let mut strings = Vec::new();
+        while !reader.is_empty() {
+            let s = reader.read_character_string()?;
+            strings.push(String::from_utf8_lossy(s));
         }
+        Ok(Record(strings))
     }
 }
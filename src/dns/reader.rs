@@ -0,0 +1,197 @@
+use super::error::DecodeError;
+use super::{Name, MAX_NAME_LEN};
+
+/// A cursor over a DNS message.
+///
+/// A reader is bounded by `end`, but keeps a reference to the whole message so that compressed
+/// names can be followed anywhere in the packet.
+#[derive(Clone)]
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    end: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self {
+            buf,
+            pos: 0,
+            end: buf.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pos >= self.end
+    }
+
+    /// Returns the number of bytes left before the end of the reader.
+    pub fn remaining(&self) -> usize {
+        self.end.saturating_sub(self.pos)
+    }
+
+    /// Returns a reader over the next `len` bytes, and advances past them.
+    pub fn sub(&mut self, len: usize) -> Result<Self, DecodeError> {
+        let start = self.pos;
+        self.read_slice(len)?;
+        Ok(Self {
+            buf: self.buf,
+            pos: start,
+            end: start + len,
+        })
+    }
+
+    pub fn read_slice(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if self.end - self.pos < len {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        let slice = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    pub fn read_u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.read_slice(1)?[0])
+    }
+
+    pub fn read_u16(&mut self) -> Result<u16, DecodeError> {
+        let bytes = self.read_slice(2)?;
+        Ok((bytes[0] as u16) << 8 | bytes[1] as u16)
+    }
+
+    pub fn read_u32(&mut self) -> Result<u32, DecodeError> {
+        let bytes = self.read_slice(4)?;
+        Ok((bytes[0] as u32) << 24
+            | (bytes[1] as u32) << 16
+            | (bytes[2] as u32) << 8
+            | bytes[3] as u32)
+    }
+
+    /// Reads a character string, that is a string prefixed by its length.
+    pub fn read_character_string(&mut self) -> Result<&'a [u8], DecodeError> {
+        let len = self.read_u8()? as usize;
+        self.read_slice(len)
+    }
+
+    /// Reads a possibly compressed domain name, which borrows the message and is only
+    /// decompressed when its labels are read.
+    ///
+    /// The whole name is checked here: compression pointers must point strictly backwards, at most
+    /// `MAX_POINTERS` of them are followed, and the name must be at most 255 bytes long once
+    /// decompressed. This guarantees that decoding a hostile name terminates quickly.
+    pub fn read_name(&mut self) -> Result<Name<'a>, DecodeError> {
+        let start = self.pos;
+        let mut encoded_len = 0;
+        // position of the label being read, and lower bound for the next pointer
+        let mut pos = self.pos;
+        let mut pointers = 0;
+
+        loop {
+            let len = *self.buf.get(pos).ok_or(DecodeError::UnexpectedEof)? as usize;
+            match len & 0xc0 {
+                0x00 => {
+                    encoded_len += len + 1;
+                    if encoded_len > MAX_NAME_LEN {
+                        return Err(DecodeError::NameTooLong);
+                    }
+                    if len == 0 {
+                        pos += 1;
+                        break;
+                    }
+                    let label = self
+                        .buf
+                        .get(pos + 1..pos + 1 + len)
+                        .ok_or(DecodeError::UnexpectedEof)?;
+                    core::str::from_utf8(label).map_err(|_| DecodeError::InvalidName)?;
+                    pos += len + 1;
+                }
+                0xc0 => {
+                    let low = *self.buf.get(pos + 1).ok_or(DecodeError::UnexpectedEof)? as usize;
+                    let target = (len & 0x3f) << 8 | low;
+                    if pointers == 0 {
+                        self.pos = pos + 2;
+                    }
+                    pointers += 1;
+                    if target >= pos || pointers > MAX_POINTERS {
+                        return Err(DecodeError::InvalidPointer);
+                    }
+                    pos = target;
+                }
+                _ => return Err(DecodeError::InvalidLabel),
+            }
+        }
+
+        if pointers == 0 {
+            self.pos = pos;
+        }
+        if self.pos > self.end {
+            return Err(DecodeError::UnexpectedEof);
+        }
+        Ok(Name::from_wire(self.buf, start))
+    }
+}
+
+/// Maximum number of compression pointers followed to read a name. A name of 255 bytes has at most
+/// 127 labels, so a well-formed message never needs more than one pointer per label.
+const MAX_POINTERS: usize = 127;
+
+/// Types that can be decoded from a DNS message.
+pub(crate) trait ReadBytes<'a>: Sized {
+    fn read_bytes(reader: &mut Reader<'a>) -> Result<Self, DecodeError>;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn read_compressed_name() {
+        let buf = [
+            5, b'l', b'o', b'c', b'a', b'l', 0, 3, b'f', b'o', b'o', 0xc0, 0, 0xc0, 7,
+        ];
+        let mut reader = Reader::new(&buf);
+        assert_eq!(reader.read_name().unwrap(), "local");
+        assert_eq!(reader.read_name().unwrap(), "foo.local");
+        assert_eq!(reader.read_name().unwrap(), "foo.local");
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn reject_pointer_loop() {
+        let buf = [0xc0, 0];
+        let mut reader = Reader::new(&buf);
+        assert_eq!(reader.read_name(), Err(DecodeError::InvalidPointer));
+    }
+
+    #[test]
+    fn reject_long_pointer_chain() {
+        // a root label followed by a chain of pointers, each pointing to the previous one
+        let mut buf = vec![0];
+        for i in 0..200u16 {
+            let target = if i == 0 { 0 } else { 1 + 2 * (i - 1) };
+            buf.extend_from_slice(&(0xc000 | target).to_be_bytes());
+        }
+        let mut reader = Reader::new(&buf);
+        reader.read_slice(1 + 2 * 100).unwrap();
+        assert!(reader.read_name().unwrap().is_root());
+        reader.read_slice(2 * 98).unwrap();
+        assert_eq!(reader.read_name(), Err(DecodeError::InvalidPointer));
+    }
+
+    #[test]
+    fn reject_name_too_long() {
+        // labels of 63 bytes, each preceded by a pointer to the previous one
+        let mut buf = vec![0];
+        let mut previous = 0u16;
+        for _ in 0..5 {
+            let start = buf.len() as u16;
+            buf.push(63);
+            buf.extend_from_slice(&[b'a'; 63]);
+            buf.extend_from_slice(&(0xc000 | previous).to_be_bytes());
+            previous = start;
+        }
+        let mut reader = Reader::new(&buf);
+        reader.read_slice(buf.len() - 2).unwrap();
+        assert_eq!(reader.read_name(), Err(DecodeError::NameTooLong));
+    }
+}
@@ -1,20 +1,71 @@
-use std::net::{Ipv4Addr, Ipv6Addr};
-use std::time::Duration;
+use alloc::borrow::Cow;
+use alloc::vec::Vec;
+use core::net::{Ipv4Addr, Ipv6Addr};
+use core::time::Duration;
 
+use super::error::{DecodeError, EncodeError};
 use super::rdata::a::Record as A;
 use super::rdata::aaaa::Record as AAAA;
+use super::rdata::cname::Record as Cname;
+use super::rdata::hinfo::Record as Hinfo;
+use super::rdata::nsec::Record as Nsec;
+use super::rdata::null::Record as Null;
+use super::rdata::opt::Record as Opt;
 use super::rdata::ptr::Record as Ptr;
 use super::rdata::srv::Record as Srv;
 use super::rdata::txt::Record as Txt;
+use super::reader::{ReadBytes, Reader};
 use super::traits::AppendBytes;
-use super::{append_qname, append_u16, append_u32, duration_to_secs};
-use dns_parser::Class;
+use super::writer::Writer;
+use super::{append_qname, append_u16, append_u32, duration_to_secs, Name};
 
-#[derive(Debug)]
+/// The class of a record (RFC 1035 §3.2.4).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Class {
+    /// The Internet.
+    IN,
+    /// The CSNET class, which is obsolete.
+    CS,
+    /// The CHAOS class.
+    CH,
+    /// Hesiod.
+    HS,
+    /// A class that is not supported, kept as its code.
+    Unknown(u16),
+}
+
+impl Class {
+    pub fn parse(code: u16) -> Self {
+        match code {
+            1 => Class::IN,
+            2 => Class::CS,
+            3 => Class::CH,
+            4 => Class::HS,
+            _ => Class::Unknown(code),
+        }
+    }
+
+    /// Returns the code of the class.
+    pub fn code(self) -> u16 {
+        match self {
+            Class::IN => 1,
+            Class::CS => 2,
+            Class::CH => 3,
+            Class::HS => 4,
+            Class::Unknown(code) => code,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ResourceRecord<'a> {
-    pub(crate) name: &'a str,
+    pub(crate) name: Name<'a>,
+    #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::secs"))]
     pub(crate) ttl: Duration,
+    #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::class"))]
     pub(crate) class: Class,
+    pub(crate) cache_flush: bool,
     pub(crate) data: RData<'a>,
 }
 
@@ -22,10 +73,11 @@ macro_rules! create_class_fn {
     ($class:ident) => {
         #[allow(non_snake_case)]
         /// Creates a `ResourceRecord` with a class of the same name, and a default ttl to 4500s.
-        pub fn $class(name: &'a str, data: RData<'a>) -> Self {
+        pub fn $class(name: impl Into<Name<'a>>, data: RData<'a>) -> Self {
             Self {
                 class: Class::$class,
-                name,
+                cache_flush: false,
+                name: name.into(),
                 data,
                 ttl: Duration::from_secs(4500),
             }
@@ -34,11 +86,12 @@ macro_rules! create_class_fn {
 }
 
 impl<'a> ResourceRecord<'a> {
-    pub fn new(name: &'a str, ttl: Duration, class: Class, data: RData<'a>) -> Self {
+    pub fn new(name: impl Into<Name<'a>>, ttl: Duration, class: Class, data: RData<'a>) -> Self {
         Self {
-            name,
+            name: name.into(),
             ttl,
             class,
+            cache_flush: false,
             data,
         }
     }
@@ -52,40 +105,215 @@ impl<'a> ResourceRecord<'a> {
         self.ttl = ttl;
         self
     }
+
+    /// Creates the resource record carrying an OPT pseudo-record. Its class and ttl are ignored,
+    /// as they are replaced by the fields of `opt` when the record is written.
+    pub fn opt(opt: Opt<'a>) -> Self {
+        Self::IN("", RData::OPT(opt))
+    }
+
+    /// Marks the record as unique, by setting the cache-flush bit. Peers receiving a unique record
+    /// replace any record they have cached with the same name, type and class (RFC 6762 §10.2).
+    pub fn unique(mut self) -> Self {
+        self.cache_flush = true;
+        self
+    }
+
+    /// Marks the record as shared, by clearing the cache-flush bit. This is the default.
+    pub fn shared(mut self) -> Self {
+        self.cache_flush = false;
+        self
+    }
+
+    /// Returns whether the cache-flush bit of the record is set.
+    pub fn is_unique(&self) -> bool {
+        self.cache_flush
+    }
+
+    /// Converts the record into a record that owns its data, so that it can be stored or sent to
+    /// another task.
+    pub fn into_owned(self) -> ResourceRecord<'static> {
+        ResourceRecord {
+            name: self.name.into_owned(),
+            ttl: self.ttl,
+            class: self.class,
+            cache_flush: self.cache_flush,
+            data: self.data.into_owned(),
+        }
+    }
+
+    /// Returns a record borrowing its data from `self`.
+    pub fn borrowed(&self) -> ResourceRecord<'_> {
+        ResourceRecord {
+            name: self.name.borrowed(),
+            ttl: self.ttl,
+            class: self.class,
+            cache_flush: self.cache_flush,
+            data: self.data.borrowed(),
+        }
+    }
+
+    pub fn name(&self) -> &Name<'a> {
+        &self.name
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn class(&self) -> Class {
+        self.class
+    }
+
+    pub fn data(&self) -> &RData<'a> {
+        &self.data
+    }
 }
 
-fn append_data<T: AppendBytes>(out: &mut Vec<u8>, data: &T) {
+fn append_data<T: AppendBytes>(out: &mut Writer, data: &T) -> Result<(), EncodeError> {
     let idx = out.len();
     append_u16(out, 0);
-    data.append_bytes(out);
-    let len = out[idx..].len() - 2;
-    out[idx] = ((len >> 8) & 0xff) as u8;
-    out[idx + 1] = (len & 0xff) as u8;
+    data.append_bytes(out)?;
+    let len = out.len() - idx - 2;
+    if len > u16::MAX as usize {
+        return Err(EncodeError::RDataTooLong);
+    }
+    out.set_u16(idx, len as u16);
+    Ok(())
+}
+
+/// Appends the fields of a record. `data` is `None` for the records of an UPDATE message that
+/// stand for a whole RRset, and have an empty rdata.
+pub(crate) fn append_record(
+    out: &mut Writer,
+    name: &Name,
+    rtype: u16,
+    class: u16,
+    ttl: u32,
+    data: Option<&RData>,
+) -> Result<(), EncodeError> {
+    append_qname(out, name)?;
+    append_u16(out, rtype);
+    append_u16(out, class);
+    append_u32(out, ttl);
+    match data {
+        Some(data) => append_data(out, data),
+        None => {
+            append_u16(out, 0);
+            Ok(())
+        }
+    }
 }
 
 impl<'a> AppendBytes for ResourceRecord<'a> {
-    fn append_bytes(&self, out: &mut Vec<u8>) {
-        append_qname(out, self.name.as_bytes());
-        append_u16(out, self.data.code());
-        append_u16(out, self.class as u16);
-        let ttl_secs = duration_to_secs(self.ttl);
-        append_u32(out, ttl_secs);
-        append_data(out, &self.data);
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        let (class, ttl) = if let RData::OPT(opt) = &self.data {
+            (opt.udp_payload_size, opt.ttl())
+        } else {
+            // the top bit of the class is the mDNS cache-flush bit
+            let cache_flush_bit = if self.cache_flush { 0x8000 } else { 0 };
+            (
+                self.class.code() | cache_flush_bit,
+                duration_to_secs(self.ttl),
+            )
+        };
+        append_record(
+            out,
+            &self.name,
+            self.data.code(),
+            class,
+            ttl,
+            Some(&self.data),
+        )
+    }
+}
+
+/// The fields of a record, before its class and rdata are interpreted.
+pub(crate) struct RawRecord<'a> {
+    pub name: Name<'a>,
+    pub rtype: u16,
+    pub class: u16,
+    pub ttl: u32,
+    pub rdata: Reader<'a>,
+}
+
+impl<'a> RawRecord<'a> {
+    pub fn read(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let name = reader.read_name()?;
+        let rtype = reader.read_u16()?;
+        let class = reader.read_u16()?;
+        let ttl = reader.read_u32()?;
+        let len = reader.read_u16()? as usize;
+        Ok(Self {
+            name,
+            rtype,
+            class,
+            ttl,
+            rdata: reader.sub(len)?,
+        })
+    }
+}
+
+impl<'a> ResourceRecord<'a> {
+    /// Reads a record from `reader`, borrowing its names and data.
+    pub(crate) fn read(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        Self::from_raw(RawRecord::read(reader)?)
+    }
+
+    /// Interprets the class and the rdata of a record.
+    pub(crate) fn from_raw(raw: RawRecord<'a>) -> Result<Self, DecodeError> {
+        let RawRecord {
+            name,
+            rtype,
+            class,
+            ttl,
+            mut rdata,
+        } = raw;
+
+        if rtype as usize == Opt::TYPE {
+            let opt = Opt::read(class, ttl, &mut rdata)?;
+            return Ok(Self::opt(opt));
+        }
+
+        let data = RData::read(rtype, &mut rdata)?;
+        if !rdata.is_empty() {
+            return Err(DecodeError::InvalidRData);
+        }
+
+        Ok(Self {
+            name,
+            ttl: Duration::from_secs(ttl as u64),
+            class: Class::parse(class & 0x7fff),
+            cache_flush: class & 0x8000 != 0,
+            data,
+        })
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RData<'a> {
     A(A),
     AAAA(AAAA),
     PTR(Ptr<'a>),
     SRV(Srv<'a>),
     TXT(Txt<'a>),
+    NSEC(Nsec<'a>),
+    CNAME(Cname<'a>),
+    HINFO(Hinfo<'a>),
+    NULL(Null<'a>),
+    OPT(Opt<'a>),
+    /// Data of a record type that is not supported, kept in its wire format.
+    Unknown {
+        rtype: u16,
+        #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::hex"))]
+        data: Cow<'a, [u8]>,
+    },
 }
 
 impl<'a> RData<'a> {
-    pub fn ptr(ptr: &'a str) -> Self {
-        Self::PTR(Ptr(ptr))
+    pub fn ptr(ptr: impl Into<Name<'a>>) -> Self {
+        Self::PTR(Ptr(ptr.into()))
     }
 
     pub fn a(addr: Ipv4Addr) -> Self {
@@ -96,59 +324,331 @@ impl<'a> RData<'a> {
         Self::AAAA(AAAA(addr))
     }
 
-    pub fn srv(port: u16, priority: u16, weight: u16, target: &'a str) -> Self {
+    pub fn srv(port: u16, priority: u16, weight: u16, target: impl Into<Name<'a>>) -> Self {
         Self::SRV(Srv {
             port,
             weight,
             priority,
-            target,
+            target: target.into(),
         })
     }
 
     pub fn txt(txt: &'a [&'a str]) -> Self {
-        Self::TXT(Txt(txt))
+        Self::TXT(txt.iter().copied().collect())
+    }
+
+    /// Creates a TXT record from owned or borrowed strings, for example a `Vec<String>`.
+    pub fn txt_strings<S: Into<Cow<'a, str>>>(txt: impl IntoIterator<Item = S>) -> Self {
+        Self::TXT(txt.into_iter().collect())
+    }
+
+    /// Creates a NSEC record asserting that `next_domain` only has records of the given `types`.
+    /// In mDNS, `next_domain` is the name of the record itself.
+    pub fn nsec(next_domain: impl Into<Name<'a>>, types: impl Into<Vec<u16>>) -> Self {
+        Self::NSEC(Nsec {
+            next_domain: next_domain.into(),
+            types: types.into(),
+        })
+    }
+
+    pub fn cname(cname: impl Into<Name<'a>>) -> Self {
+        Self::CNAME(Cname(cname.into()))
+    }
+
+    pub fn hinfo(cpu: impl Into<Cow<'a, str>>, os: impl Into<Cow<'a, str>>) -> Self {
+        Self::HINFO(Hinfo {
+            cpu: cpu.into(),
+            os: os.into(),
+        })
+    }
+
+    pub fn null(data: impl Into<Cow<'a, [u8]>>) -> Self {
+        Self::NULL(Null(data.into()))
+    }
+
+    /// Creates data of type `rtype`, that is written as is.
+    pub fn unknown(rtype: u16, data: impl Into<Cow<'a, [u8]>>) -> Self {
+        Self::Unknown {
+            rtype,
+            data: data.into(),
+        }
+    }
+
+    /// Converts the data into data that owns its names and strings.
+    pub fn into_owned(self) -> RData<'static> {
+        match self {
+            RData::A(data) => RData::A(data),
+            RData::AAAA(data) => RData::AAAA(data),
+            RData::PTR(data) => RData::PTR(data.into_owned()),
+            RData::SRV(data) => RData::SRV(data.into_owned()),
+            RData::TXT(data) => RData::TXT(data.into_owned()),
+            RData::NSEC(data) => RData::NSEC(data.into_owned()),
+            RData::CNAME(data) => RData::CNAME(data.into_owned()),
+            RData::HINFO(data) => RData::HINFO(data.into_owned()),
+            RData::NULL(data) => RData::NULL(data.into_owned()),
+            RData::OPT(data) => RData::OPT(data.into_owned()),
+            RData::Unknown { rtype, data } => RData::Unknown {
+                rtype,
+                data: Cow::Owned(data.into_owned()),
+            },
+        }
+    }
+
+    /// Returns data borrowing its names and strings from `self`.
+    pub fn borrowed(&self) -> RData<'_> {
+        match self {
+            RData::A(data) => RData::A(data.clone()),
+            RData::AAAA(data) => RData::AAAA(data.clone()),
+            RData::PTR(data) => RData::PTR(data.borrowed()),
+            RData::SRV(data) => RData::SRV(data.borrowed()),
+            RData::TXT(data) => RData::TXT(data.borrowed()),
+            RData::NSEC(data) => RData::NSEC(data.borrowed()),
+            RData::CNAME(data) => RData::CNAME(data.borrowed()),
+            RData::HINFO(data) => RData::HINFO(data.borrowed()),
+            RData::NULL(data) => RData::NULL(data.borrowed()),
+            RData::OPT(data) => RData::OPT(data.borrowed()),
+            RData::Unknown { rtype, data } => RData::Unknown {
+                rtype: *rtype,
+                data: Cow::Borrowed(data),
+            },
+        }
     }
 }
 
 impl AppendBytes for RData<'_> {
-    fn append_bytes(&self, out: &mut Vec<u8>) {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
         match self {
             RData::A(data) => data.append_bytes(out),
             RData::AAAA(data) => data.append_bytes(out),
             RData::PTR(data) => data.append_bytes(out),
             RData::SRV(data) => data.append_bytes(out),
             RData::TXT(data) => data.append_bytes(out),
+            RData::NSEC(data) => data.append_bytes(out),
+            RData::CNAME(data) => data.append_bytes(out),
+            RData::HINFO(data) => data.append_bytes(out),
+            RData::NULL(data) => data.append_bytes(out),
+            RData::OPT(data) => data.append_bytes(out),
+            RData::Unknown { data, .. } => {
+                out.extend_from_slice(data);
+                Ok(())
+            }
         }
     }
 }
 
 impl RData<'_> {
-    fn code(&self) -> u16 {
+    pub(crate) fn code(&self) -> u16 {
         match self {
             RData::A(_) => A::TYPE as u16,
             RData::AAAA(_) => AAAA::TYPE as u16,
             RData::PTR(_) => Ptr::TYPE as u16,
             RData::SRV(_) => Srv::TYPE as u16,
             RData::TXT(_) => Txt::TYPE as u16,
+            RData::NSEC(_) => Nsec::TYPE as u16,
+            RData::CNAME(_) => Cname::TYPE as u16,
+            RData::HINFO(_) => Hinfo::TYPE as u16,
+            RData::NULL(_) => Null::TYPE as u16,
+            RData::OPT(_) => Opt::TYPE as u16,
+            RData::Unknown { rtype, .. } => *rtype,
         }
     }
 }
 
+impl<'a> RData<'a> {
+    pub(crate) fn read(rtype: u16, reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let data = match rtype as usize {
+            A::TYPE => RData::A(A::read_bytes(reader)?),
+            AAAA::TYPE => RData::AAAA(AAAA::read_bytes(reader)?),
+            Ptr::TYPE => RData::PTR(Ptr::read_bytes(reader)?),
+            Srv::TYPE => RData::SRV(Srv::read_bytes(reader)?),
+            Txt::TYPE => RData::TXT(Txt::read_bytes(reader)?),
+            Nsec::TYPE => RData::NSEC(Nsec::read_bytes(reader)?),
+            Cname::TYPE => RData::CNAME(Cname::read_bytes(reader)?),
+            Hinfo::TYPE => RData::HINFO(Hinfo::read_bytes(reader)?),
+            Null::TYPE => RData::NULL(Null::read_bytes(reader)?),
+            _ => RData::Unknown {
+                rtype,
+                data: read_unknown(rtype, reader)?,
+            },
+        };
+        Ok(data)
+    }
+}
+
+/// Reads the rdata of a type that is not supported by `RData`.
+///
+/// The names in the rdata of the types defined by RFC 1035 may be compressed (RFC 3597 §4), and
+/// would point to unrelated bytes once the rdata is written in another message. The rdata of those
+/// types is copied with its names decompressed when it contains a pointer.
+fn read_unknown<'a>(rtype: u16, reader: &mut Reader<'a>) -> Result<Cow<'a, [u8]>, DecodeError> {
+    // the length of the fields before the names, the number of names, and the length of the
+    // fields after them
+    let (prefix, names, suffix) = match rtype {
+        // NS, MD, MF, MB, MG and MR
+        2..=4 | 7..=9 => (0, 1, 0),
+        // SOA
+        6 => (0, 2, 20),
+        // MINFO
+        14 => (0, 2, 0),
+        // MX
+        15 => (2, 1, 0),
+        _ => return Ok(Cow::Borrowed(reader.read_slice(reader.remaining())?)),
+    };
+
+    let raw = reader.clone().read_slice(reader.remaining())?;
+    let mut out = Writer::growable(false);
+    out.extend_from_slice(reader.read_slice(prefix)?);
+    for _ in 0..names {
+        append_qname(&mut out, &reader.read_name()?).map_err(|_| DecodeError::InvalidRData)?;
+    }
+    out.extend_from_slice(reader.read_slice(suffix)?);
+
+    let data = out.into_vec();
+    match raw.starts_with(&data) {
+        true => Ok(Cow::Borrowed(&raw[..data.len()])),
+        false => Ok(Cow::Owned(data)),
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use super::super::writer::to_vec;
     use super::*;
-    use std::net::Ipv4Addr;
+    use core::net::Ipv4Addr;
 
     #[test]
     fn build_resource_record() {
         let data = RData::A(A(Ipv4Addr::new(0, 0, 0, 0)));
         let record = ResourceRecord {
-            name: "_service._tcp.local",
+            name: Name::from("_service._tcp.local"),
             ttl: Duration::from_secs(4500),
             class: Class::IN,
+            cache_flush: false,
             data,
         };
-        let mut buffer = Vec::new();
-        record.append_bytes(&mut buffer);
+        to_vec(&record).unwrap();
+    }
+
+    #[test]
+    fn read_resource_record() {
+        let record = ResourceRecord::IN(
+            "marin._myservice._tcp.local",
+            RData::srv(8594, 0, 0, "marin.local"),
+        )
+        .unique();
+        let buffer = to_vec(&record).unwrap();
+
+        let mut reader = Reader::new(&buffer);
+        let read = ResourceRecord::read(&mut reader).unwrap();
+        assert!(reader.is_empty());
+        assert_eq!(read, record);
+        assert!(read.is_unique());
+    }
+
+    #[test]
+    fn owned_resource_record() {
+        fn assert_send<T: Send + 'static>(_: &T) {}
+
+        let name = String::from("marin._myservice._tcp.local");
+        let strings = vec![String::from("foo=bar")];
+        let record = ResourceRecord::IN(name.as_str(), RData::txt_strings(&strings));
+        let owned = record.clone().into_owned();
+        drop(strings);
+        assert_send(&owned);
+        assert_eq!(owned, record_from_strings());
+        assert_eq!(owned.borrowed(), owned);
+    }
+
+    fn record_from_strings() -> ResourceRecord<'static> {
+        ResourceRecord::IN(
+            String::from("marin._myservice._tcp.local"),
+            RData::txt_strings(vec![String::from("foo=bar")]),
+        )
+    }
+
+    #[test]
+    fn unknown_record_round_trip() {
+        // SPF record, which `RData` doesn't support
+        let data = [6, b'v', b'=', b's', b'p', b'f', b'1'];
+        let record = ResourceRecord::IN("marin.local", RData::unknown(99, &data[..]));
+        let buffer = to_vec(&record).unwrap();
+
+        let mut reader = Reader::new(&buffer);
+        let read = ResourceRecord::read(&mut reader).unwrap();
+        assert_eq!(read, record);
+        assert_eq!(
+            read.data(),
+            &RData::Unknown {
+                rtype: 99,
+                data: Cow::Borrowed(&data[..])
+            }
+        );
+    }
+
+    #[test]
+    fn decompress_unknown_record() {
+        // marin.local, then an MX record for local, whose exchange points to marin.local
+        #[rustfmt::skip]
+        let buffer = [
+            5, b'm', b'a', b'r', b'i', b'n', 5, b'l', b'o', b'c', b'a', b'l', 0,
+            0xc0, 6, 0, 15, 0, 1, 0, 0, 0, 120, 0, 4,
+            0, 10, 0xc0, 0,
+        ];
+        let mut reader = Reader::new(&buffer);
+        reader.read_name().unwrap();
+        let read = ResourceRecord::read(&mut reader).unwrap();
+        assert!(reader.is_empty());
+
+        let exchange = [
+            5, b'm', b'a', b'r', b'i', b'n', 5, b'l', b'o', b'c', b'a', b'l', 0,
+        ];
+        let mut data = vec![0, 10];
+        data.extend_from_slice(&exchange);
+        assert_eq!(read.data(), &RData::unknown(15, data.clone()));
+
+        // the record is written in another message without the pointer
+        let buffer = to_vec(&read).unwrap();
+        assert_eq!(&buffer[buffer.len() - data.len()..], &data[..]);
+        let read = ResourceRecord::read(&mut Reader::new(&buffer)).unwrap();
+        assert_eq!(read.data(), &RData::unknown(15, data));
+    }
+
+    #[test]
+    fn cname_hinfo_null_round_trip() {
+        let records = [
+            ResourceRecord::IN("alias.local", RData::cname("marin.local")),
+            ResourceRecord::IN("marin.local", RData::hinfo("ARM64", "Linux")),
+            ResourceRecord::IN("marin.local", RData::null(&[0xde, 0xad, 0xbe, 0xef][..])),
+        ];
+        for record in &records {
+            let buffer = to_vec(record).unwrap();
+
+            let mut reader = Reader::new(&buffer);
+            let read = ResourceRecord::read(&mut reader).unwrap();
+            assert_eq!(&read, record);
+        }
+    }
+
+    #[test]
+    fn encode_cache_flush_bit() {
+        let record = ResourceRecord::IN("marin.local", RData::a(Ipv4Addr::new(10, 0, 0, 1)));
+        for (record, class) in [(record.clone().unique(), 0x80), (record.shared(), 0x00)] {
+            let buffer = to_vec(&record).unwrap();
+            // name (13 bytes) and type come before the class
+            assert_eq!(&buffer[15..17], &[class, 0x01]);
+        }
+    }
+
+    #[test]
+    fn unknown_class_round_trip() {
+        let mut record = ResourceRecord::IN("marin.local", RData::a(Ipv4Addr::new(10, 0, 0, 1)));
+        record.class = Class::Unknown(42);
+        let buffer = to_vec(&record.clone().unique()).unwrap();
+        assert_eq!(&buffer[15..17], &[0x80, 42]);
+
+        let read = ResourceRecord::read(&mut Reader::new(&buffer)).unwrap();
+        assert_eq!(read.class(), Class::Unknown(42));
+        assert!(read.is_unique());
+        assert_eq!(read, record.unique());
     }
 }
@@ -0,0 +1,415 @@
+//! Serde support, enabled by the `serde` feature.
+//!
+//! Human-readable formats get the same representations as the presentation format: names are
+//! dotted strings, classes and types are mnemonics, ttls are seconds, addresses use their usual
+//! notation, and opaque data is written in hexadecimal. Other formats get the numeric values and
+//! raw bytes.
+
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Write};
+use core::time::Duration;
+
+use serde::de::{self, Deserializer};
+use serde::ser::Serializer;
+use serde::{Deserialize, Serialize};
+
+use super::header::{OpCode, PacketHeader, RCode};
+use super::presentation::{class_name, parse_mnemonic, type_name, CLASSES, TYPES};
+use super::{Class, Name, QueryClass, QueryType};
+
+impl Serialize for Name<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de, 'a> Deserialize<'de> for Name<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Name::from)
+    }
+}
+
+/// The fields of a `PacketHeader`, with its flags and codes decoded.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct Header {
+    id: u16,
+    query: bool,
+    opcode: OpCode,
+    authoritative: bool,
+    truncated: bool,
+    recursion_desired: bool,
+    recursion_available: bool,
+    authentic_data: bool,
+    checking_disabled: bool,
+    rcode: RCode,
+    questions: u16,
+    answers: u16,
+    authorities: u16,
+    additionals: u16,
+}
+
+impl From<PacketHeader> for Header {
+    fn from(header: PacketHeader) -> Self {
+        Self {
+            id: header.id(),
+            query: header.is_query(),
+            opcode: header.opcode(),
+            authoritative: header.aa(),
+            truncated: header.tc(),
+            recursion_desired: header.rd(),
+            recursion_available: header.ra(),
+            authentic_data: header.ad(),
+            checking_disabled: header.cd(),
+            rcode: header.rcode(),
+            questions: header.qd_count(),
+            answers: header.an_count(),
+            authorities: header.ns_count(),
+            additionals: header.ar_count(),
+        }
+    }
+}
+
+impl From<Header> for PacketHeader {
+    fn from(header: Header) -> Self {
+        let mut out = PacketHeader::default();
+        out.set_id(header.id)
+            .set_query(header.query)
+            .set_opcode(header.opcode)
+            .set_aa(header.authoritative)
+            .set_tc(header.truncated)
+            .set_rd(header.recursion_desired)
+            .set_ra(header.recursion_available)
+            .set_ad(header.authentic_data)
+            .set_cd(header.checking_disabled)
+            .set_rcode(header.rcode)
+            .set_qd_count(header.questions)
+            .set_an_count(header.answers)
+            .set_ns_count(header.authorities)
+            .set_ar_count(header.additionals);
+        out
+    }
+}
+
+fn serialize_mnemonic<S: Serializer>(
+    value: u16,
+    name: impl fmt::Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    if serializer.is_human_readable() {
+        serializer.collect_str(&name)
+    } else {
+        serializer.serialize_u16(value)
+    }
+}
+
+fn deserialize_mnemonic<'de, D: Deserializer<'de>>(
+    table: &[(u16, &str)],
+    prefix: &str,
+    deserializer: D,
+) -> Result<u16, D::Error> {
+    if deserializer.is_human_readable() {
+        let s = String::deserialize(deserializer)?;
+        parse_mnemonic(table, prefix, &s)
+            .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&s), &prefix))
+    } else {
+        u16::deserialize(deserializer)
+    }
+}
+
+fn unsupported<E: de::Error>(value: u16, expected: &str) -> E {
+    de::Error::invalid_value(de::Unexpected::Unsigned(value as u64), &expected)
+}
+
+/// A class, as its mnemonic.
+pub(crate) mod class {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(class: &Class, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_mnemonic(class.code(), class_name(class.code(), false), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Class, D::Error> {
+        let value = deserialize_mnemonic(CLASSES, "CLASS", deserializer)?;
+        Ok(Class::parse(value))
+    }
+}
+
+/// The type of a question, as its mnemonic.
+pub(crate) mod qtype {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(qtype: &QueryType, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_mnemonic(qtype.code(), type_name(qtype.code()), serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<QueryType, D::Error> {
+        let value = deserialize_mnemonic(TYPES, "TYPE", deserializer)?;
+        Ok(QueryType::parse(value))
+    }
+}
+
+/// The class of a question, as its mnemonic.
+pub(crate) mod qclass {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(qclass: &QueryClass, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_mnemonic(
+            *qclass as u16,
+            class_name(*qclass as u16, false),
+            serializer,
+        )
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<QueryClass, D::Error> {
+        let value = deserialize_mnemonic(CLASSES, "CLASS", deserializer)?;
+        QueryClass::parse(value).map_err(|_| unsupported(value, "a supported query class"))
+    }
+}
+
+/// A ttl, as a number of seconds.
+pub(crate) mod secs {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(ttl: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u32(super::super::duration_to_secs(*ttl))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u32::deserialize(deserializer).map(|secs| Duration::from_secs(secs as u64))
+    }
+}
+
+fn to_hex(bytes: &[u8], separator: &str) -> String {
+    let mut out = String::with_capacity(bytes.len() * (2 + separator.len()));
+    for (i, byte) in bytes.iter().enumerate() {
+        if i > 0 {
+            out.push_str(separator);
+        }
+        let _ = write!(out, "{:02x}", byte);
+    }
+    out
+}
+
+/// Parses bytes written by `to_hex`, returning `None` if `s` is not well-formed.
+fn from_hex(s: &str, separator: &str) -> Option<Vec<u8>> {
+    let byte = |digits: &str| match digits.len() {
+        2 if digits.bytes().all(|b| b.is_ascii_hexdigit()) => u8::from_str_radix(digits, 16).ok(),
+        _ => None,
+    };
+    if s.is_empty() {
+        Some(Vec::new())
+    } else if separator.is_empty() {
+        (0..s.len())
+            .step_by(2)
+            .map(|i| s.get(i..i + 2).and_then(byte))
+            .collect()
+    } else {
+        s.split(separator).map(byte).collect()
+    }
+}
+
+/// Opaque data, as a string of hexadecimal digits.
+pub(crate) mod hex {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&to_hex(data, ""))
+        } else {
+            data.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, 'a, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Cow<'a, [u8]>, D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            from_hex(&s, "")
+                .map(Cow::Owned)
+                .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&s), &"hex digits"))
+        } else {
+            Vec::deserialize(deserializer).map(Cow::Owned)
+        }
+    }
+}
+
+/// Optional opaque data, as a string of hexadecimal digits.
+pub(crate) mod hex_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        data: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Hex<'a>(#[serde(with = "super::hex")] &'a [u8]);
+        data.as_deref().map(Hex).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        #[derive(Deserialize)]
+        struct Hex(#[serde(with = "super::hex")] Cow<'static, [u8]>);
+        let data = Option::<Hex>::deserialize(deserializer)?;
+        Ok(data.map(|Hex(data)| data.into_owned()))
+    }
+}
+
+/// A MAC address, as colon-separated hexadecimal bytes.
+pub(crate) mod mac {
+    use super::*;
+    use core::convert::TryFrom;
+
+    pub fn serialize<S: Serializer>(mac: &[u8; 6], serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&to_hex(mac, ":"))
+        } else {
+            mac.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 6], D::Error> {
+        if deserializer.is_human_readable() {
+            let s = String::deserialize(deserializer)?;
+            from_hex(&s, ":")
+                .and_then(|bytes| <[u8; 6]>::try_from(bytes.as_slice()).ok())
+                .ok_or_else(|| de::Error::invalid_value(de::Unexpected::Str(&s), &"a MAC address"))
+        } else {
+            <[u8; 6]>::deserialize(deserializer)
+        }
+    }
+}
+
+/// An optional MAC address, as colon-separated hexadecimal bytes.
+pub(crate) mod mac_option {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(
+        mac: &Option<[u8; 6]>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        struct Mac<'a>(#[serde(with = "super::mac")] &'a [u8; 6]);
+        mac.as_ref().map(Mac).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<[u8; 6]>, D::Error> {
+        #[derive(Deserialize)]
+        struct Mac(#[serde(with = "super::mac")] [u8; 6]);
+        let mac = Option::<Mac>::deserialize(deserializer)?;
+        Ok(mac.map(|Mac(mac)| mac))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::super::rdata::opt::{EdnsOption, Owner, Record as Opt};
+    use super::super::{RData, ResourceRecord};
+    use super::*;
+    use core::net::Ipv4Addr;
+    use serde_json::json;
+
+    #[test]
+    fn records_are_human_readable() {
+        let records = vec![
+            ResourceRecord::IN("marin.local", RData::a(Ipv4Addr::new(192, 168, 1, 7))).unique(),
+            ResourceRecord::IN("marin._myservice._tcp.local", RData::txt(&["path=/"]))
+                .set_ttl(Duration::from_secs(120)),
+            ResourceRecord::CH("marin.local", RData::unknown(65, &[0xca, 0xfe][..])),
+        ];
+        let value = serde_json::to_value(&records).unwrap();
+        assert_eq!(
+            value,
+            json!([
+                {
+                    "name": "marin.local",
+                    "ttl": 4500,
+                    "class": "IN",
+                    "cache_flush": true,
+                    "data": { "A": "192.168.1.7" },
+                },
+                {
+                    "name": "marin._myservice._tcp.local",
+                    "ttl": 120,
+                    "class": "IN",
+                    "cache_flush": false,
+                    "data": { "TXT": ["path=/"] },
+                },
+                {
+                    "name": "marin.local",
+                    "ttl": 4500,
+                    "class": "CH",
+                    "cache_flush": false,
+                    "data": { "Unknown": { "rtype": 65, "data": "cafe" } },
+                },
+            ])
+        );
+
+        let read: Vec<ResourceRecord> = serde_json::from_value(value).unwrap();
+        assert_eq!(read, records);
+    }
+
+    #[test]
+    fn opt_round_trip() {
+        let mut opt = Opt::new(1440);
+        opt.options.push(EdnsOption::Owner(Owner {
+            version: 0,
+            sequence: 3,
+            primary_mac: [0x00, 0x1b, 0x63, 0xaa, 0xbb, 0xcc],
+            wakeup_mac: None,
+            password: Some(vec![1, 2, 3, 4]),
+        }));
+        let record = ResourceRecord::opt(opt);
+
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(json.contains(r#""primary_mac":"00:1b:63:aa:bb:cc","wakeup_mac":null"#));
+        assert!(json.contains(r#""password":"01020304""#));
+        assert_eq!(
+            serde_json::from_str::<ResourceRecord>(&json).unwrap(),
+            record
+        );
+    }
+
+    #[test]
+    fn header_round_trip() {
+        let mut header = PacketHeader::default();
+        header
+            .set_id(42)
+            .set_query(false)
+            .set_aa(true)
+            .set_rcode(RCode::NameError)
+            .set_an_count(2);
+
+        let value = serde_json::to_value(&header).unwrap();
+        assert_eq!(value["query"], false);
+        assert_eq!(value["authoritative"], true);
+        assert_eq!(value["opcode"], "QUERY");
+        assert_eq!(value["rcode"], "NameError");
+        assert_eq!(
+            serde_json::from_value::<PacketHeader>(value).unwrap(),
+            header
+        );
+    }
+
+    #[test]
+    fn reject_malformed_values() {
+        let invalid = [
+            json!({ "name": "a", "ttl": 1, "class": "XX", "cache_flush": false, "data": { "A": "10.0.0.1" } }),
+            json!({ "name": "a", "ttl": 1, "class": "IN", "cache_flush": false, "data": { "NULL": "abc" } }),
+            json!({ "name": "a", "ttl": 1, "class": "IN", "cache_flush": false, "data": { "NULL": "+f" } }),
+        ];
+        for value in invalid.iter() {
+            assert!(
+                serde_json::from_value::<ResourceRecord>(value.clone()).is_err(),
+                "{}",
+                value
+            );
+        }
+    }
+}
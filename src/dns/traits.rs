@@ -1,3 +1,22 @@
+use super::error::EncodeError;
+use super::writer::{self, Writer};
+use bytes::BufMut;
+
+/// Encodes a value, such as a record or its rdata, in its wire format.
 pub trait AppendBytes {
-    fn append_bytes(&self, out: &mut Vec<u8>);
+    /// Appends the value to `out`.
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError>;
+
+    /// Writes the value into `buf`, without compression, and returns its length. Returns
+    /// `EncodeError::BufferTooSmall` with its length if it doesn't fit in `buf`.
+    fn write_into(&self, buf: &mut [u8]) -> Result<usize, EncodeError> {
+        writer::write_into(buf, false, |out| self.append_bytes(out))
+    }
+
+    /// Writes the value at the end of `buf`, without compression, and returns its length.
+    /// Returns `EncodeError::BufferTooSmall` with its length if the remaining capacity of `buf` is
+    /// too small.
+    fn write_into_buf(&self, buf: &mut impl BufMut) -> Result<usize, EncodeError> {
+        writer::write_into_buf(buf, false, |out| self.append_bytes(out))
+    }
 }
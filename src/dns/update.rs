@@ -0,0 +1,341 @@
+//! Entries of DNS UPDATE messages (RFC 2136).
+//!
+//! An UPDATE message reuses the sections of a DNS message: the question section holds the zone
+//! to update, the answer section the prerequisites, and the authority section the updates.
+//! Entries that apply to a whole RRset or name are records with the `ANY` or `NONE` class and an
+//! empty rdata.
+
+use super::error::{DecodeError, EncodeError};
+use super::reader::Reader;
+use super::resource_record::{append_record, RawRecord};
+use super::traits::AppendBytes;
+use super::writer::Writer;
+use super::{Name, RData, ResourceRecord};
+
+/// The `ANY` class and type, matching every RRset.
+pub(crate) const ANY: u16 = 255;
+/// The `NONE` class, used to assert that something doesn't exist, or to delete a single record.
+pub(crate) const NONE: u16 = 254;
+
+/// A prerequisite of an UPDATE message, which must hold for the update to be applied
+/// (RFC 2136 §2.4).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Prerequisite<'a> {
+    /// An RRset of type `rtype` exists for `name`, whatever its records.
+    Exists { name: Name<'a>, rtype: u16 },
+    /// `record` is part of its RRset. The RRset must have exactly the records given by the
+    /// prerequisites of this kind with the same name and type. The ttl of the record is ignored.
+    ExistsWith(ResourceRecord<'a>),
+    /// No RRset of type `rtype` exists for `name`.
+    NotExists { name: Name<'a>, rtype: u16 },
+    /// `name` has at least one record.
+    NameInUse(Name<'a>),
+    /// `name` has no record.
+    NameNotInUse(Name<'a>),
+}
+
+impl<'a> Prerequisite<'a> {
+    /// Converts the prerequisite into one that owns its data.
+    pub fn into_owned(self) -> Prerequisite<'static> {
+        match self {
+            Prerequisite::Exists { name, rtype } => Prerequisite::Exists {
+                name: name.into_owned(),
+                rtype,
+            },
+            Prerequisite::ExistsWith(record) => Prerequisite::ExistsWith(record.into_owned()),
+            Prerequisite::NotExists { name, rtype } => Prerequisite::NotExists {
+                name: name.into_owned(),
+                rtype,
+            },
+            Prerequisite::NameInUse(name) => Prerequisite::NameInUse(name.into_owned()),
+            Prerequisite::NameNotInUse(name) => Prerequisite::NameNotInUse(name.into_owned()),
+        }
+    }
+
+    /// Reads a prerequisite from the answer section of an UPDATE message.
+    pub(crate) fn read(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let raw = RawRecord::read(reader)?;
+        let prerequisite = match (raw.class, raw.rtype) {
+            (ANY, ANY) => Prerequisite::NameInUse(raw.name),
+            (ANY, rtype) => Prerequisite::Exists {
+                name: raw.name,
+                rtype,
+            },
+            (NONE, ANY) => Prerequisite::NameNotInUse(raw.name),
+            (NONE, rtype) => Prerequisite::NotExists {
+                name: raw.name,
+                rtype,
+            },
+            _ => return ResourceRecord::from_raw(raw).map(Prerequisite::ExistsWith),
+        };
+        if raw.ttl != 0 || !raw.rdata.is_empty() {
+            return Err(DecodeError::InvalidRData);
+        }
+        Ok(prerequisite)
+    }
+}
+
+impl AppendBytes for Prerequisite<'_> {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        match self {
+            Prerequisite::Exists { name, rtype } => append_record(out, name, *rtype, ANY, 0, None),
+            Prerequisite::ExistsWith(record) => append_record(
+                out,
+                &record.name,
+                record.data.code(),
+                record.class.code(),
+                0,
+                Some(&record.data),
+            ),
+            Prerequisite::NotExists { name, rtype } => {
+                append_record(out, name, *rtype, NONE, 0, None)
+            }
+            Prerequisite::NameInUse(name) => append_record(out, name, ANY, ANY, 0, None),
+            Prerequisite::NameNotInUse(name) => append_record(out, name, ANY, NONE, 0, None),
+        }
+    }
+}
+
+/// An update of an UPDATE message (RFC 2136 §2.5).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Update<'a> {
+    /// Adds `record` to its RRset.
+    Add(ResourceRecord<'a>),
+    /// Deletes the RRset of type `rtype` of `name`.
+    DeleteRRset { name: Name<'a>, rtype: u16 },
+    /// Deletes every RRset of `name`.
+    DeleteName(Name<'a>),
+    /// Deletes the record of `name` with the given data from its RRset.
+    Delete { name: Name<'a>, data: RData<'a> },
+}
+
+impl<'a> Update<'a> {
+    /// Creates an update deleting `record` from its RRset. Its ttl and class are ignored.
+    pub fn delete(record: ResourceRecord<'a>) -> Self {
+        Update::Delete {
+            name: record.name,
+            data: record.data,
+        }
+    }
+
+    /// Converts the update into one that owns its data.
+    pub fn into_owned(self) -> Update<'static> {
+        match self {
+            Update::Add(record) => Update::Add(record.into_owned()),
+            Update::DeleteRRset { name, rtype } => Update::DeleteRRset {
+                name: name.into_owned(),
+                rtype,
+            },
+            Update::DeleteName(name) => Update::DeleteName(name.into_owned()),
+            Update::Delete { name, data } => Update::Delete {
+                name: name.into_owned(),
+                data: data.into_owned(),
+            },
+        }
+    }
+
+    /// Reads an update from the authority section of an UPDATE message.
+    pub(crate) fn read(reader: &mut Reader<'a>) -> Result<Self, DecodeError> {
+        let mut raw = RawRecord::read(reader)?;
+        if raw.class == ANY {
+            if raw.ttl != 0 || !raw.rdata.is_empty() {
+                return Err(DecodeError::InvalidRData);
+            }
+            return Ok(match raw.rtype {
+                ANY => Update::DeleteName(raw.name),
+                rtype => Update::DeleteRRset {
+                    name: raw.name,
+                    rtype,
+                },
+            });
+        }
+        if raw.class == NONE {
+            if raw.ttl != 0 {
+                return Err(DecodeError::InvalidRData);
+            }
+            let data = RData::read(raw.rtype, &mut raw.rdata)?;
+            if !raw.rdata.is_empty() {
+                return Err(DecodeError::InvalidRData);
+            }
+            return Ok(Update::Delete {
+                name: raw.name,
+                data,
+            });
+        }
+
+        ResourceRecord::from_raw(raw).map(Update::Add)
+    }
+}
+
+impl AppendBytes for Update<'_> {
+    fn append_bytes(&self, out: &mut Writer) -> Result<(), EncodeError> {
+        match self {
+            Update::Add(record) => record.append_bytes(out),
+            Update::DeleteRRset { name, rtype } => append_record(out, name, *rtype, ANY, 0, None),
+            Update::DeleteName(name) => append_record(out, name, ANY, ANY, 0, None),
+            Update::Delete { name, data } => {
+                append_record(out, name, data.code(), NONE, 0, Some(data))
+            }
+        }
+    }
+}
+
+/// Iterator over the prerequisites of an UPDATE message. Iteration stops after the first error.
+pub struct Prerequisites<'a> {
+    pub(crate) reader: Reader<'a>,
+    pub(crate) remaining: u16,
+}
+
+impl<'a> Iterator for Prerequisites<'a> {
+    type Item = Result<Prerequisite<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let prerequisite = Prerequisite::read(&mut self.reader);
+        if prerequisite.is_err() {
+            self.remaining = 0;
+        }
+        Some(prerequisite)
+    }
+}
+
+/// Iterator over the updates of an UPDATE message. Iteration stops after the first error.
+pub struct Updates<'a> {
+    pub(crate) reader: Reader<'a>,
+    pub(crate) remaining: u16,
+}
+
+impl<'a> Iterator for Updates<'a> {
+    type Item = Result<Update<'a>, DecodeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let update = Update::read(&mut self.reader);
+        if update.is_err() {
+            self.remaining = 0;
+        }
+        Some(update)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::dns::{Message, MessageRef, OpCode, PacketBuilder, QueryClass, QueryType};
+    use core::net::Ipv4Addr;
+
+    fn registration<'a>() -> PacketBuilder<'a> {
+        let mut packet = PacketBuilder::update("local", QueryClass::IN);
+        packet
+            .add_prerequisite(Prerequisite::NameNotInUse("marin.local".into()))
+            .add_prerequisite(Prerequisite::Exists {
+                name: "local".into(),
+                rtype: 6,
+            })
+            .add_update(Update::DeleteRRset {
+                name: "marin.local".into(),
+                rtype: 1,
+            })
+            .add_update(Update::Add(ResourceRecord::IN(
+                "marin.local",
+                RData::a(Ipv4Addr::new(192, 168, 1, 7)),
+            )))
+            .add_update(Update::delete(ResourceRecord::IN(
+                "marin.local",
+                RData::a(Ipv4Addr::new(10, 0, 0, 1)),
+            )))
+            .add_update(Update::DeleteName("old.local".into()));
+        packet
+    }
+
+    #[test]
+    fn update_round_trip() {
+        let packet = registration().build();
+        let message = Message::parse(&packet).unwrap();
+
+        assert_eq!(message.header().opcode(), OpCode::UPDATE);
+        let zone = message.zone().unwrap();
+        assert_eq!(zone.name, "local");
+        assert_eq!(zone.qtype, QueryType::SOA);
+        assert_eq!(zone.qclass, QueryClass::IN);
+        assert!(message.answers().is_empty());
+        assert!(message.authorities().is_empty());
+        assert_eq!(
+            message.prerequisites(),
+            &[
+                Prerequisite::NameNotInUse("marin.local".into()),
+                Prerequisite::Exists {
+                    name: "local".into(),
+                    rtype: 6,
+                },
+            ]
+        );
+        assert_eq!(message.updates().len(), 4);
+        assert_eq!(
+            message.updates()[2],
+            Update::Delete {
+                name: "marin.local".into(),
+                data: RData::a(Ipv4Addr::new(10, 0, 0, 1)),
+            }
+        );
+        assert_eq!(message.updates()[3], Update::DeleteName("old.local".into()));
+    }
+
+    #[test]
+    fn write_rrset_entries() {
+        let packet = registration().build();
+        // the first prerequisite follows the header and the zone
+        let prerequisite = &packet[12 + 11..];
+        #[rustfmt::skip]
+        assert_eq!(
+            &prerequisite[..21],
+            &[
+                5, b'm', b'a', b'r', b'i', b'n', 0xc0, 12,
+                0, 255, 0, 254, 0, 0, 0, 0, 0, 0,
+                // the next name is a pointer to the zone
+                0xc0, 12, 0,
+            ]
+        );
+    }
+
+    #[test]
+    fn reject_rrset_entries_with_data() {
+        let mut packet = PacketBuilder::update("local", QueryClass::IN);
+        packet.add_prerequisite(Prerequisite::ExistsWith(ResourceRecord::IN(
+            "marin.local",
+            RData::a(Ipv4Addr::new(192, 168, 1, 7)),
+        )));
+        let mut bytes = packet.build();
+        // turn the class of the record into ANY, which must have an empty rdata
+        let class = bytes.len() - 4 - 2 - 4 - 2;
+        bytes[class..class + 2].copy_from_slice(&[0, 255]);
+
+        let message = MessageRef::parse(&bytes).unwrap();
+        let errors = message.prerequisites().collect::<Vec<_>>();
+        assert_eq!(errors, vec![Err(DecodeError::InvalidRData)]);
+        assert_eq!(
+            Message::parse(&bytes).unwrap_err(),
+            DecodeError::InvalidRData
+        );
+    }
+
+    #[test]
+    fn display_update_message() {
+        let message = Message::parse(&registration().build()).unwrap();
+        let output = message.to_string();
+        assert!(output.contains(";; ZONE SECTION:\n;local. IN SOA"));
+        assert!(
+            output.contains(";; PREREQUISITE SECTION:\nmarin.local. 0 NONE ANY\nlocal. 0 ANY SOA")
+        );
+        assert!(output.contains("\nmarin.local. 0 ANY A\nmarin.local. 4500 IN A 192.168.1.7"));
+        assert!(output.contains("\nmarin.local. 0 NONE A 10.0.0.1\nold.local. 0 ANY ANY"));
+    }
+}
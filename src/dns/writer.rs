@@ -0,0 +1,380 @@
+use super::error::EncodeError;
+use super::traits::AppendBytes;
+use alloc::vec::Vec;
+use bytes::buf::UninitSlice;
+use bytes::BufMut;
+use core::mem::MaybeUninit;
+
+/// Pointers can only address the first 16KiB of a message, as their offset is 14 bits long.
+const MAX_POINTER_OFFSET: usize = 0x3fff;
+
+/// Maximum number of name suffixes remembered for compression. Once it is reached, names are
+/// still written, but are no longer used as compression targets.
+const MAX_NAMES: usize = 256;
+
+/// Output buffer of a DNS message, passed to `AppendBytes::append_bytes`.
+///
+/// The writer writes into a slice supplied by the caller, or into the spare capacity of a
+/// `BufMut`. Bytes written past the end of the slice are kept in a vector, which is only allocated
+/// when the slice is too small, so that the whole message can still be compressed and its length
+/// is exact. The caller checks the length against the length of the slice. A writer over an empty
+/// slice writes everything into the vector, which grows as needed, and a counting writer only
+/// counts the bytes.
+///
+/// When compression is enabled, the writer remembers the offset of every name suffix it writes,
+/// so that later occurrences can be replaced by a pointer, as described in RFC 1035 §4.1.4. The
+/// suffixes are compared with the bytes already written, so that compression doesn't allocate. A
+/// counting writer keeps a copy of the suffixes instead.
+pub struct Writer<'b> {
+    buf: &'b mut [MaybeUninit<u8>],
+    /// The bytes written past the end of `buf`, or `None` if they are only counted.
+    overflow: Option<Vec<u8>>,
+    /// The name suffixes remembered by a counting writer, in their wire form, one after the other.
+    suffixes: Vec<u8>,
+    len: usize,
+    compress: bool,
+    names: [u16; MAX_NAMES],
+    names_len: usize,
+}
+
+impl<'b> Writer<'b> {
+    pub(crate) fn new(buf: &'b mut [u8], compress: bool) -> Self {
+        // SAFETY: the writer only writes initialized bytes into `buf`.
+        Self::uninit(
+            unsafe { UninitSlice::new(buf).as_uninit_slice_mut() },
+            compress,
+        )
+    }
+
+    fn uninit(buf: &'b mut [MaybeUninit<u8>], compress: bool) -> Self {
+        Self {
+            buf,
+            overflow: Some(Vec::new()),
+            suffixes: Vec::new(),
+            len: 0,
+            compress,
+            names: [0; MAX_NAMES],
+            names_len: 0,
+        }
+    }
+
+    /// Returns a writer into a vector that grows as bytes are written.
+    pub(crate) fn growable(compress: bool) -> Self {
+        Self::new(&mut [], compress)
+    }
+
+    /// Returns a writer that counts the bytes written without keeping them.
+    pub(crate) fn counting(compress: bool) -> Self {
+        Self {
+            overflow: None,
+            ..Self::growable(compress)
+        }
+    }
+
+    /// Returns the number of bytes written, including the ones that didn't fit in the buffer.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns whether nothing was written yet.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns a copy of the bytes written.
+    pub(crate) fn to_vec(&self) -> Vec<u8> {
+        let written = self.len.min(self.buf.len());
+        let mut bytes = Vec::with_capacity(self.len);
+        bytes.extend((0..written).filter_map(|idx| self.get(idx)));
+        bytes.extend_from_slice(self.overflow.as_deref().unwrap_or_default());
+        bytes
+    }
+
+    /// Returns the bytes written, without copying them if the writer is growable.
+    pub(crate) fn into_vec(self) -> Vec<u8> {
+        match self.overflow {
+            Some(overflow) if self.buf.is_empty() => overflow,
+            _ => self.to_vec(),
+        }
+    }
+
+    pub fn push(&mut self, byte: u8) {
+        match (self.buf.get_mut(self.len), &mut self.overflow) {
+            (Some(b), _) => *b = MaybeUninit::new(byte),
+            (None, Some(overflow)) => overflow.push(byte),
+            (None, None) => (),
+        }
+        self.len += 1;
+    }
+
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        let fits = self.buf.len().saturating_sub(self.len).min(bytes.len());
+        let (head, tail) = bytes.split_at(fits);
+        if let Some(buf) = self.buf.get_mut(self.len..self.len + fits) {
+            for (b, &byte) in buf.iter_mut().zip(head) {
+                *b = MaybeUninit::new(byte);
+            }
+        }
+        if let Some(overflow) = &mut self.overflow {
+            overflow.extend_from_slice(tail);
+        }
+        self.len += bytes.len();
+    }
+
+    /// Overwrites the two bytes at `idx` with `value`.
+    pub(crate) fn set_u16(&mut self, idx: usize, value: u16) {
+        self.write_at(idx, &value.to_be_bytes());
+    }
+
+    /// Overwrites the bytes already written at `idx` with `bytes`.
+    pub(crate) fn write_at(&mut self, idx: usize, bytes: &[u8]) {
+        for (idx, &byte) in (idx..self.len).zip(bytes) {
+            match (self.buf.get_mut(idx), &mut self.overflow) {
+                (Some(b), _) => *b = MaybeUninit::new(byte),
+                (None, Some(overflow)) => overflow[idx - self.buf.len()] = byte,
+                (None, None) => (),
+            }
+        }
+    }
+
+    /// Returns the byte written at `idx`, if any and if it was kept.
+    fn get(&self, idx: usize) -> Option<u8> {
+        if idx >= self.len {
+            return None;
+        }
+        match self.buf.get(idx) {
+            // SAFETY: the bytes of `buf` before `len` were written.
+            Some(byte) => Some(unsafe { byte.assume_init() }),
+            None => Some(self.overflow.as_ref()?[idx - self.buf.len()]),
+        }
+    }
+
+    /// Returns the offset of a previous occurrence of `suffix`, in its wire form, if compression
+    /// is enabled.
+    pub(crate) fn name_offset(&self, suffix: &[u8]) -> Option<u16> {
+        if !self.compress {
+            return None;
+        }
+        let mut names = self.names[..self.names_len].iter().copied();
+        if self.overflow.is_some() {
+            return names.find(|&offset| self.is_name_at(offset as usize, suffix));
+        }
+        let mut suffixes = &self.suffixes[..];
+        for offset in names {
+            let (name, rest) = suffixes.split_at(wire_len(suffixes));
+            if name == suffix {
+                return Some(offset);
+            }
+            suffixes = rest;
+        }
+        None
+    }
+
+    /// Records that the name suffix `suffix`, in its wire form, is written at the current offset.
+    pub(crate) fn insert_name(&mut self, suffix: &[u8]) {
+        let offset = self.len;
+        if self.compress && offset <= MAX_POINTER_OFFSET && self.names_len < MAX_NAMES {
+            self.names[self.names_len] = offset as u16;
+            self.names_len += 1;
+            if self.overflow.is_none() {
+                self.suffixes.extend_from_slice(suffix);
+            }
+        }
+    }
+
+    /// Returns whether the name written at `offset` is `suffix`, following compression pointers.
+    fn is_name_at(&self, mut offset: usize, suffix: &[u8]) -> bool {
+        let mut idx = 0;
+        loop {
+            let len = match self.get(offset) {
+                Some(len) => len as usize,
+                None => return false,
+            };
+            if len & 0xc0 == 0xc0 {
+                let target = match self.get(offset + 1) {
+                    Some(low) => (len & 0x3f) << 8 | low as usize,
+                    None => return false,
+                };
+                if target >= offset {
+                    return false;
+                }
+                offset = target;
+                continue;
+            }
+            let label = match suffix.get(idx..idx + 1 + len) {
+                Some(label) => label,
+                None => return false,
+            };
+            if (offset..).zip(label).any(|(i, &b)| self.get(i) != Some(b)) {
+                return false;
+            }
+            if len == 0 {
+                return true;
+            }
+            offset += 1 + len;
+            idx += 1 + len;
+        }
+    }
+
+    /// Removes everything written after `len`, including the names recorded for compression.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.len = self.len.min(len);
+        if let Some(overflow) = &mut self.overflow {
+            overflow.truncate(self.len.saturating_sub(self.buf.len()));
+        }
+        let names = &self.names[..self.names_len];
+        let names_len = names
+            .iter()
+            .take_while(|&&offset| (offset as usize) < len)
+            .count();
+        if self.overflow.is_none() {
+            let kept = (0..names_len).fold(0, |end, _| end + wire_len(&self.suffixes[end..]));
+            self.suffixes.truncate(kept);
+        }
+        self.names_len = names_len;
+    }
+}
+
+/// Returns the length of the wire form of the name at the start of `name`, which isn't compressed.
+fn wire_len(name: &[u8]) -> usize {
+    let mut idx = 0;
+    while name[idx] != 0 {
+        idx += 1 + name[idx] as usize;
+    }
+    idx + 1
+}
+
+/// Writes into `buf` with `write`, and returns the number of bytes written. Returns
+/// `EncodeError::BufferTooSmall` with the number of bytes needed if they don't fit in `buf`.
+pub(crate) fn write_into(
+    buf: &mut [u8],
+    compress: bool,
+    write: impl FnOnce(&mut Writer) -> Result<(), EncodeError>,
+) -> Result<usize, EncodeError> {
+    let capacity = buf.len();
+    let mut out = Writer::new(buf, compress);
+    write(&mut out)?;
+    match out.len() {
+        len if len > capacity => Err(EncodeError::BufferTooSmall(len)),
+        len => Ok(len),
+    }
+}
+
+/// Writes at the end of `buf` with `write`, and returns the number of bytes written. Returns
+/// `EncodeError::BufferTooSmall` with the number of bytes needed if the remaining capacity of `buf`
+/// is too small.
+///
+/// The bytes are written straight into the spare capacity of `buf`. They are only copied from a
+/// vector if they don't fit in its current chunk, which isn't contiguous with the next one.
+pub(crate) fn write_into_buf(
+    buf: &mut impl BufMut,
+    compress: bool,
+    write: impl FnOnce(&mut Writer) -> Result<(), EncodeError>,
+) -> Result<usize, EncodeError> {
+    let remaining = buf.remaining_mut();
+    // SAFETY: the writer only writes initialized bytes into the chunk.
+    let mut out = Writer::uninit(unsafe { buf.chunk_mut().as_uninit_slice_mut() }, compress);
+    write(&mut out)?;
+    let Writer {
+        buf: chunk,
+        overflow,
+        len,
+        ..
+    } = out;
+    if len > remaining {
+        return Err(EncodeError::BufferTooSmall(len));
+    }
+    let written = len.min(chunk.len());
+    // SAFETY: the first `written` bytes of the chunk were written.
+    unsafe { buf.advance_mut(written) };
+    buf.put_slice(overflow.as_deref().unwrap_or_default());
+    Ok(len)
+}
+
+/// Encodes `value` without compression into a new vector.
+pub(crate) fn to_vec(value: &impl AppendBytes) -> Result<Vec<u8>, EncodeError> {
+    let mut out = Writer::growable(false);
+    value.append_bytes(&mut out)?;
+    Ok(out.into_vec())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn keep_bytes_past_the_end() {
+        let mut buf = [0; 4];
+        let mut out = Writer::new(&mut buf, false);
+        out.extend_from_slice(&[1, 2, 3]);
+        out.extend_from_slice(&[4, 5, 6]);
+        out.push(7);
+        out.set_u16(3, 0x0809);
+        assert_eq!(out.len(), 7);
+        assert_eq!(out.to_vec(), &[1, 2, 3, 8, 9, 6, 7]);
+        out.truncate(5);
+        assert_eq!(out.into_vec(), &[1, 2, 3, 8, 9]);
+        assert_eq!(buf, [1, 2, 3, 8]);
+    }
+
+    #[test]
+    fn find_written_names() {
+        // the names don't fit in the buffer, and are still found past its end, or in the copy
+        // kept by a counting writer
+        let mut buf = [0; 8];
+        for mut out in [Writer::new(&mut buf, true), Writer::counting(true)] {
+            // foo.local, then bar.local with a pointer to local
+            out.insert_name(&[3, b'f', b'o', b'o', 5, b'l', b'o', b'c', b'a', b'l', 0]);
+            out.extend_from_slice(&[3, b'f', b'o', b'o']);
+            out.insert_name(&[5, b'l', b'o', b'c', b'a', b'l', 0]);
+            out.extend_from_slice(&[5, b'l', b'o', b'c', b'a', b'l', 0]);
+            out.insert_name(&[3, b'b', b'a', b'r', 5, b'l', b'o', b'c', b'a', b'l', 0]);
+            out.extend_from_slice(&[3, b'b', b'a', b'r', 0xc0, 4]);
+            assert_eq!(out.len(), 17);
+
+            assert_eq!(
+                out.name_offset(&[5, b'l', b'o', b'c', b'a', b'l', 0]),
+                Some(4)
+            );
+            assert_eq!(
+                out.name_offset(&[3, b'b', b'a', b'r', 5, b'l', b'o', b'c', b'a', b'l', 0]),
+                Some(11)
+            );
+            assert_eq!(out.name_offset(&[3, b'b', b'a', b'r', 0]), None);
+
+            out.truncate(11);
+            assert_eq!(
+                out.name_offset(&[3, b'b', b'a', b'r', 5, b'l', b'o', b'c', b'a', b'l', 0]),
+                None
+            );
+            assert_eq!(
+                out.name_offset(&[5, b'l', b'o', b'c', b'a', b'l', 0]),
+                Some(4)
+            );
+        }
+    }
+
+    #[test]
+    fn write_into_buf_mut() {
+        let mut buf = bytes::BytesMut::with_capacity(16);
+        buf.extend_from_slice(&[0xff]);
+        let len = write_into_buf(&mut buf, false, |out| {
+            out.extend_from_slice(&[1, 2, 3]);
+            out.set_u16(1, 0x0405);
+            Ok(())
+        });
+        assert_eq!(len, Ok(3));
+        assert_eq!(&buf[..], &[0xff, 1, 4, 5]);
+
+        // the chunk of a chain is not contiguous with the next one
+        let mut first = [0; 2];
+        let mut second = [0; 4];
+        let mut chain = (&mut first[..]).chain_mut(&mut second[..]);
+        let len = write_into_buf(&mut chain, false, |out| {
+            out.extend_from_slice(&[1, 2, 3, 4, 5]);
+            Ok(())
+        });
+        assert_eq!(len, Ok(5));
+        assert_eq!((first, second), ([1, 2], [3, 4, 5, 0]));
+    }
+}
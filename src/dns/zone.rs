@@ -0,0 +1,320 @@
+//! Reading and writing records in master file format (RFC 1035 §5).
+//!
+//! Only a subset of the format is supported: `$ORIGIN` and `$TTL` directives, relative names and
+//! `@`, omitted owners, ttls and classes, comments, and entries split over several lines with
+//! parentheses. Records use the presentation format of [`ResourceRecord`]'s `Display`
+//! implementation.
+
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use core::mem;
+use core::time::Duration;
+
+use super::error::{ParseError, ZoneError};
+use super::presentation::{join, parse_mnemonic, parse_rdata, tokenize, Token, CLASSES, TYPES};
+use super::rdata::opt::Record as Opt;
+use super::{Class, Name, RData, ResourceRecord};
+
+/// Reads the records of a master file. Names are relative to the root until the first `$ORIGIN`
+/// directive.
+pub fn read(s: &str) -> Result<Vec<ResourceRecord<'static>>, ZoneError> {
+    let mut parser = Parser {
+        origin: Name::root(),
+        default_ttl: None,
+        previous: None,
+    };
+    let mut records = Vec::new();
+
+    let mut entry = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    let mut blank_owner = false;
+    for (idx, line) in s.lines().enumerate() {
+        let tokens = tokenize(line).map_err(|error| ZoneError {
+            line: idx + 1,
+            error,
+        })?;
+        if depth == 0 {
+            start = idx + 1;
+            blank_owner = line.starts_with(char::is_whitespace);
+        }
+
+        for token in tokens {
+            if token.is("(") {
+                depth += 1;
+            } else if token.is(")") {
+                depth = depth.checked_sub(1).ok_or(ZoneError {
+                    line: idx + 1,
+                    error: ParseError::UnbalancedParentheses,
+                })?;
+            } else {
+                entry.push(token);
+            }
+        }
+        if depth > 0 || entry.is_empty() {
+            continue;
+        }
+
+        let tokens = mem::take(&mut entry);
+        let record = parser
+            .entry(blank_owner, &tokens)
+            .map_err(|error| ZoneError { line: start, error })?;
+        records.extend(record);
+    }
+
+    if depth > 0 {
+        return Err(ZoneError {
+            line: start,
+            error: ParseError::UnbalancedParentheses,
+        });
+    }
+    Ok(records)
+}
+
+/// Writes records in master file format, one per line and with absolute names, so that they can
+/// be read back by [`read`].
+///
+/// The cache-flush bit of mDNS records is not kept, as other tools would read their class as
+/// `CLASS32769`, and OPT pseudo-records, which only belong in a message, are skipped.
+pub fn write<'a, 'b: 'a>(
+    out: &mut impl fmt::Write,
+    records: impl IntoIterator<Item = &'a ResourceRecord<'b>>,
+) -> fmt::Result {
+    for record in records {
+        if let RData::OPT(_) = record.data {
+            continue;
+        }
+        let mut record = record.borrowed();
+        record.cache_flush = false;
+        writeln!(out, "{}", record)?;
+    }
+    Ok(())
+}
+
+struct Parser {
+    origin: Name<'static>,
+    /// The ttl set by the last `$TTL` directive.
+    default_ttl: Option<u32>,
+    /// The owner, ttl and class of the previous record, used when they are omitted.
+    previous: Option<(Name<'static>, u32, u16)>,
+}
+
+impl Parser {
+    /// Resolves a name of the file, which is relative to the origin unless it ends with a dot.
+    fn name(&self, text: &str) -> Result<Name<'static>, ParseError> {
+        if text == "@" {
+            return Ok(self.origin.clone());
+        }
+        let name = text.parse::<Name>()?;
+        // a trailing dot preceded by an odd number of backslashes is part of the last label
+        let backslashes = text
+            .trim_end_matches('.')
+            .bytes()
+            .rev()
+            .take_while(|&b| b == b'\\')
+            .count();
+        if text.ends_with('.') && backslashes % 2 == 0 {
+            return Ok(name);
+        }
+        Ok(Name::from_labels(
+            name.labels()
+                .chain(self.origin.labels())
+                .map(str::to_string)
+                .collect::<Vec<_>>(),
+        ))
+    }
+
+    /// Parses an entry of the file, and returns the record it contains, if it is not a directive.
+    fn entry(
+        &mut self,
+        blank_owner: bool,
+        tokens: &[Token],
+    ) -> Result<Option<ResourceRecord<'static>>, ParseError> {
+        if !tokens[0].quoted && tokens[0].text.starts_with('$') {
+            self.directive(tokens)?;
+            return Ok(None);
+        }
+
+        let (owner, mut fields) = if blank_owner {
+            let (owner, _, _) = self
+                .previous
+                .as_ref()
+                .ok_or(ParseError::MissingField("name"))?;
+            (owner.clone(), tokens)
+        } else {
+            (self.name(&tokens[0].text)?, &tokens[1..])
+        };
+
+        // the ttl and the class are both optional, and may come in any order
+        let mut ttl = None;
+        let mut class = None;
+        while let Some(token) = fields.first() {
+            if ttl.is_none() && token.text.bytes().all(|b| b.is_ascii_digit()) {
+                let value = token.text.parse::<u32>();
+                ttl = Some(value.map_err(|_| ParseError::InvalidTtl(token.text.clone()))?);
+            } else if class.is_none() {
+                match parse_mnemonic(CLASSES, "CLASS", &token.text) {
+                    Some(value) => class = Some(value),
+                    None => break,
+                }
+            } else {
+                break;
+            }
+            fields = &fields[1..];
+        }
+
+        let rtype = fields.first().ok_or(ParseError::MissingField("type"))?;
+        let rtype = parse_mnemonic(TYPES, "TYPE", &rtype.text)
+            .filter(|&rtype| rtype as usize != Opt::TYPE)
+            .ok_or_else(|| ParseError::InvalidType(rtype.text.clone()))?;
+        let ttl = ttl
+            .or(self.default_ttl)
+            .or_else(|| self.previous.as_ref().map(|(_, ttl, _)| *ttl))
+            .ok_or(ParseError::MissingField("ttl"))?;
+        let class = class
+            .or_else(|| self.previous.as_ref().map(|(_, _, class)| *class))
+            .unwrap_or(Class::IN.code());
+        let data = parse_rdata(rtype, &fields[1..], &|s| self.name(s))?;
+
+        let mut record = ResourceRecord::new(
+            owner.clone(),
+            Duration::from_secs(ttl as u64),
+            Class::parse(class & 0x7fff),
+            data,
+        );
+        record.cache_flush = class & 0x8000 != 0;
+        // the cache-flush bit only applies to the record that sets it
+        self.previous = Some((owner, ttl, class & 0x7fff));
+        Ok(Some(record))
+    }
+
+    fn directive(&mut self, tokens: &[Token]) -> Result<(), ParseError> {
+        let invalid = || ParseError::InvalidDirective(join(tokens));
+        match (tokens[0].text.as_str(), tokens.get(1)) {
+            ("$ORIGIN", Some(origin)) if tokens.len() == 2 => {
+                self.origin = self.name(&origin.text)?;
+            }
+            ("$TTL", Some(ttl)) if tokens.len() == 2 => {
+                self.default_ttl = Some(ttl.text.parse().map_err(|_| invalid())?);
+            }
+            _ => return Err(invalid()),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use core::net::{Ipv4Addr, Ipv6Addr};
+
+    const CATALOG: &str = r#"
+$ORIGIN local.
+$TTL 120
+; a service advertised by marin
+_myservice._tcp      4500 PTR marin._myservice._tcp
+marin._myservice._tcp IN 4500 SRV 0 0 8594 marin
+                     TXT ( "path=/"
+                           "version=2" )
+marin                CLASS32769 A 192.168.1.7
+                     AAAA fe80::1
+@                    PTR marin.local.
+"#;
+
+    #[test]
+    fn read_catalog() {
+        let records = read(CATALOG).unwrap();
+        let secs = Duration::from_secs;
+        let expected = vec![
+            ResourceRecord::IN(
+                "_myservice._tcp.local",
+                RData::ptr("marin._myservice._tcp.local"),
+            ),
+            ResourceRecord::IN(
+                "marin._myservice._tcp.local",
+                RData::srv(8594, 0, 0, "marin.local"),
+            ),
+            ResourceRecord::IN(
+                "marin._myservice._tcp.local",
+                RData::txt(&["path=/", "version=2"]),
+            )
+            .set_ttl(secs(120)),
+            ResourceRecord::IN("marin.local", RData::a(Ipv4Addr::new(192, 168, 1, 7)))
+                .set_ttl(secs(120))
+                .unique(),
+            ResourceRecord::IN(
+                "marin.local",
+                RData::aaaa(Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1)),
+            )
+            .set_ttl(secs(120)),
+            ResourceRecord::IN("local", RData::ptr("marin.local")).set_ttl(secs(120)),
+        ];
+        assert_eq!(records, expected);
+        assert!(records[3].is_unique());
+    }
+
+    #[test]
+    fn write_and_read_back() {
+        let mut records = read(CATALOG).unwrap();
+        records.push(ResourceRecord::opt(Opt::new(1440)));
+        let mut out = String::new();
+        write(&mut out, &records).unwrap();
+        assert!(
+            out.starts_with("_myservice._tcp.local. 4500 IN PTR marin._myservice._tcp.local.\n")
+        );
+        assert!(out.contains("marin.local. 120 IN A 192.168.1.7\n"));
+
+        // the OPT record is skipped, and the cache-flush bit is not kept
+        records.pop();
+        records[3] = records[3].clone().shared();
+        assert_eq!(read(&out).unwrap(), records);
+    }
+
+    #[test]
+    fn read_escaped_names() {
+        let records =
+            read("$ORIGIN _myservice._tcp.local.\nMarin's\\032Mac\\. 60 IN A 10.0.0.1").unwrap();
+        assert_eq!(records[0].name().labels().next(), Some("Marin's Mac."));
+        assert_eq!(records[0].name().labels().count(), 4);
+    }
+
+    #[test]
+    fn read_invalid_files() {
+        let errors = [
+            (
+                "marin.local. IN A 10.0.0.1",
+                1,
+                ParseError::MissingField("ttl"),
+            ),
+            ("$TTL 60\n  A 10.0.0.1", 2, ParseError::MissingField("name")),
+            (
+                "$INCLUDE other.zone",
+                1,
+                ParseError::InvalidDirective("$INCLUDE other.zone".to_string()),
+            ),
+            (
+                "\n\nmarin 60 TXT ( \"a\"\n",
+                3,
+                ParseError::UnbalancedParentheses,
+            ),
+            (
+                "marin 60 A 10.0.0.1 )",
+                1,
+                ParseError::UnbalancedParentheses,
+            ),
+            (
+                "marin 60 MX 10 mail",
+                1,
+                ParseError::InvalidRData("10 mail".to_string()),
+            ),
+        ];
+        for (file, line, error) in errors.iter() {
+            let expected = ZoneError {
+                line: *line,
+                error: error.clone(),
+            };
+            assert_eq!(read(file).unwrap_err(), expected, "{}", file);
+        }
+    }
+}
@@ -1 +1,130 @@
-pub type Error = Box<dyn std::error::Error>;
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::dns::{DecodeError, EncodeError, ParseError, ZoneError};
+
+/// Error returned by the mDNS service.
+#[derive(Debug)]
+pub enum Error {
+    /// An I/O operation failed. `context` tells what was being done.
+    Io {
+        context: &'static str,
+        source: io::Error,
+    },
+    /// A socket couldn't be bound to `addr`, for example because another process uses the port
+    /// without allowing it to be reused.
+    Bind { addr: SocketAddr, source: io::Error },
+    /// A socket couldn't join the multicast `group`.
+    JoinMulticast { group: IpAddr, source: io::Error },
+    /// The settings of the service are inconsistent.
+    InvalidConfig(&'static str),
+    /// A DNS message couldn't be encoded.
+    Encode(EncodeError),
+    /// A DNS message couldn't be decoded.
+    Decode(DecodeError),
+    /// A record in presentation format couldn't be parsed.
+    Parse(ParseError),
+    /// A master file couldn't be read.
+    Zone(ZoneError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io { context, source } => write!(f, "failed to {}: {}", context, source),
+            Error::Bind { addr, source } => write!(f, "failed to bind to {}: {}", addr, source),
+            Error::JoinMulticast { group, source } => {
+                write!(f, "failed to join multicast group {}: {}", group, source)
+            }
+            Error::InvalidConfig(reason) => write!(f, "invalid configuration: {}", reason),
+            Error::Encode(error) => write!(f, "failed to encode message: {}", error),
+            Error::Decode(error) => write!(f, "failed to decode message: {}", error),
+            Error::Parse(error) => write!(f, "failed to parse record: {}", error),
+            Error::Zone(error) => write!(f, "failed to read master file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. }
+            | Error::Bind { source, .. }
+            | Error::JoinMulticast { source, .. } => Some(source),
+            Error::InvalidConfig(_) => None,
+            Error::Encode(error) => Some(error),
+            Error::Decode(error) => Some(error),
+            Error::Parse(error) => Some(error),
+            Error::Zone(error) => Some(error),
+        }
+    }
+}
+
+impl From<EncodeError> for Error {
+    fn from(error: EncodeError) -> Self {
+        Error::Encode(error)
+    }
+}
+
+impl From<DecodeError> for Error {
+    fn from(error: DecodeError) -> Self {
+        Error::Decode(error)
+    }
+}
+
+impl From<ParseError> for Error {
+    fn from(error: ParseError) -> Self {
+        Error::Parse(error)
+    }
+}
+
+impl From<ZoneError> for Error {
+    fn from(error: ZoneError) -> Self {
+        Error::Zone(error)
+    }
+}
+
+/// Adds a context to I/O errors.
+pub(crate) trait Context<T> {
+    /// Converts the error into `Error::Io`, where `context` describes the failed operation, such
+    /// as "set multicast ttl".
+    fn context(self, context: &'static str) -> Result<T, Error>;
+}
+
+impl<T> Context<T> for io::Result<T> {
+    fn context(self, context: &'static str) -> Result<T, Error> {
+        self.map_err(|source| Error::Io { context, source })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::error::Error as _;
+
+    #[test]
+    fn error_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<Error>();
+    }
+
+    #[test]
+    fn display_with_context() {
+        let error = Error::JoinMulticast {
+            group: IpAddr::from([224, 0, 0, 251]),
+            source: io::Error::new(io::ErrorKind::AddrNotAvailable, "no such device"),
+        };
+        assert_eq!(
+            error.to_string(),
+            "failed to join multicast group 224.0.0.251: no such device"
+        );
+        assert!(error.source().is_some());
+
+        let error = Error::from(DecodeError::UnexpectedEof);
+        assert_eq!(
+            error.to_string(),
+            "failed to decode message: unexpected end of message"
+        );
+    }
+}
@@ -0,0 +1,266 @@
+//! Network interfaces the service joins the multicast groups on.
+
+use std::io;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::error::Error;
+
+/// An address of a network interface that supports multicast. Interfaces with several addresses
+/// are listed once per address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Interface {
+    pub name: String,
+    pub index: u32,
+    pub addr: IpAddr,
+    /// The length of the prefix of the subnet of `addr`.
+    pub prefix_len: u8,
+}
+
+/// A change of the interfaces of the system, returned by `MdnsService::next` as
+/// `Packet::Interface`. The multicast groups are joined and left accordingly, on the selected
+/// interfaces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum InterfaceEvent {
+    InterfaceUp { name: String, index: u32 },
+    InterfaceDown { name: String, index: u32 },
+    AddressAdded(Interface),
+    AddressRemoved(Interface),
+}
+
+/// Selects interfaces by name or by subnet, to allow or deny them with
+/// `MdnsServiceBuilder::allow_interface` and `MdnsServiceBuilder::deny_interface`.
+///
+/// Filters can be parsed from strings: `10.0.0.0/8` and `fe80::/10` are subnets, `eth0` is a
+/// name, and `docker*` matches every name starting with `docker`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InterfaceFilter {
+    /// Matches the interface with this name. A trailing `*` matches any suffix.
+    Name(String),
+    /// Matches the interfaces with an address in this subnet.
+    Subnet { addr: IpAddr, prefix_len: u8 },
+}
+
+impl InterfaceFilter {
+    pub fn matches(&self, interface: &Interface) -> bool {
+        match self {
+            InterfaceFilter::Name(name) => match name.strip_suffix('*') {
+                Some(prefix) => interface.name.starts_with(prefix),
+                None => interface.name == *name,
+            },
+            InterfaceFilter::Subnet { addr, prefix_len } => {
+                in_subnet(interface.addr, *addr, *prefix_len)
+            }
+        }
+    }
+}
+
+impl FromStr for InterfaceFilter {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some(subnet) => subnet,
+            None if s.is_empty() => return Err(Error::InvalidConfig("empty interface filter")),
+            None => return Ok(InterfaceFilter::Name(s.to_string())),
+        };
+        let invalid = || Error::InvalidConfig("invalid subnet");
+        let addr = addr.parse::<IpAddr>().map_err(|_| invalid())?;
+        let prefix_len = prefix_len.parse::<u8>().map_err(|_| invalid())?;
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix_len > max_len {
+            return Err(invalid());
+        }
+        Ok(InterfaceFilter::Subnet { addr, prefix_len })
+    }
+}
+
+/// Returns whether `addr` is in the subnet `subnet/prefix_len`.
+fn in_subnet(addr: IpAddr, subnet: IpAddr, prefix_len: u8) -> bool {
+    let mask = |bits: u32| match prefix_len as u32 {
+        0 => 0,
+        len => u128::MAX << (bits - len.min(bits)),
+    };
+    match (addr, subnet) {
+        (IpAddr::V4(addr), IpAddr::V4(subnet)) => {
+            let mask = mask(32) as u32;
+            u32::from(addr) & mask == u32::from(subnet) & mask
+        }
+        (IpAddr::V6(addr), IpAddr::V6(subnet)) => {
+            let mask = mask(128);
+            u128::from(addr) & mask == u128::from(subnet) & mask
+        }
+        _ => false,
+    }
+}
+
+/// The interfaces allowed and denied by the settings of the service.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Selection {
+    pub allow: Vec<InterfaceFilter>,
+    pub deny: Vec<InterfaceFilter>,
+}
+
+impl Selection {
+    /// Returns whether `interface` is selected: it must match one of the allowed filters, if any,
+    /// and none of the denied ones.
+    pub fn selects(&self, interface: &Interface) -> bool {
+        (self.allow.is_empty() || self.allow.iter().any(|filter| filter.matches(interface)))
+            && !self.deny.iter().any(|filter| filter.matches(interface))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+}
+
+/// Lists the addresses of the interfaces that are up and support multicast, or returns `None` if
+/// interfaces can't be listed on this platform.
+#[cfg(unix)]
+pub(crate) fn list() -> io::Result<Option<Vec<Interface>>> {
+    use std::ffi::CStr;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+    use std::ptr;
+
+    let mut addrs = ptr::null_mut();
+    // SAFETY: `addrs` is a valid pointer, set to a list that is freed below.
+    if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let mut interfaces = Vec::new();
+    let mut cursor = addrs;
+    while !cursor.is_null() {
+        // SAFETY: the entries of the list stay valid until it is freed.
+        let entry = unsafe { &*cursor };
+        cursor = entry.ifa_next;
+
+        let flags = entry.ifa_flags as libc::c_int;
+        if entry.ifa_addr.is_null() || flags & libc::IFF_UP == 0 || flags & libc::IFF_MULTICAST == 0
+        {
+            continue;
+        }
+
+        // SAFETY: the address is read as the structure matching its family, and the netmask has
+        // the same family as the address.
+        let (addr, prefix_len) = unsafe {
+            match (*entry.ifa_addr).sa_family as libc::c_int {
+                libc::AF_INET => {
+                    let addr = &*(entry.ifa_addr as *const libc::sockaddr_in);
+                    let addr = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+                    let prefix_len = match entry.ifa_netmask.is_null() {
+                        true => 32,
+                        false => {
+                            let mask = &*(entry.ifa_netmask as *const libc::sockaddr_in);
+                            mask.sin_addr.s_addr.count_ones() as u8
+                        }
+                    };
+                    (IpAddr::V4(addr), prefix_len)
+                }
+                libc::AF_INET6 => {
+                    let addr = &*(entry.ifa_addr as *const libc::sockaddr_in6);
+                    let addr = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+                    let prefix_len = match entry.ifa_netmask.is_null() {
+                        true => 128,
+                        false => {
+                            let mask = &*(entry.ifa_netmask as *const libc::sockaddr_in6);
+                            u128::from_ne_bytes(mask.sin6_addr.s6_addr).count_ones() as u8
+                        }
+                    };
+                    (IpAddr::V6(addr), prefix_len)
+                }
+                _ => continue,
+            }
+        };
+
+        // SAFETY: the name is a NUL-terminated string.
+        let name = unsafe { CStr::from_ptr(entry.ifa_name) };
+        interfaces.push(Interface {
+            name: name.to_string_lossy().into_owned(),
+            // SAFETY: same as above.
+            index: unsafe { libc::if_nametoindex(entry.ifa_name) },
+            addr,
+            prefix_len,
+        });
+    }
+
+    // SAFETY: the list was returned by `getifaddrs`, and is no longer used.
+    unsafe { libc::freeifaddrs(addrs) };
+    Ok(Some(interfaces))
+}
+
+/// Lists the addresses of the interfaces that are up and support multicast, or returns `None` if
+/// interfaces can't be listed on this platform.
+#[cfg(not(unix))]
+pub(crate) fn list() -> io::Result<Option<Vec<Interface>>> {
+    Ok(None)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn interface(name: &str, addr: &str) -> Interface {
+        Interface {
+            name: name.to_string(),
+            index: 1,
+            addr: addr.parse().unwrap(),
+            prefix_len: 24,
+        }
+    }
+
+    #[test]
+    fn parse_filters() {
+        assert_eq!(
+            "eth0".parse::<InterfaceFilter>().unwrap(),
+            InterfaceFilter::Name("eth0".to_string())
+        );
+        assert_eq!(
+            "fe80::/10".parse::<InterfaceFilter>().unwrap(),
+            InterfaceFilter::Subnet {
+                addr: "fe80::".parse().unwrap(),
+                prefix_len: 10
+            }
+        );
+        for invalid in ["", "10.0.0.0/33", "10.0.0/8", "10.0.0.0/"].iter() {
+            assert!(invalid.parse::<InterfaceFilter>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn match_filters() {
+        let docker = "docker*".parse::<InterfaceFilter>().unwrap();
+        assert!(docker.matches(&interface("docker0", "172.17.0.1")));
+        assert!(!docker.matches(&interface("eth0", "192.168.1.7")));
+
+        let private = "192.168.0.0/16".parse::<InterfaceFilter>().unwrap();
+        assert!(private.matches(&interface("eth0", "192.168.1.7")));
+        assert!(!private.matches(&interface("eth0", "10.0.0.1")));
+        assert!(!private.matches(&interface("eth0", "fe80::1")));
+
+        let any = "::/0".parse::<InterfaceFilter>().unwrap();
+        assert!(any.matches(&interface("eth0", "fe80::1")));
+    }
+
+    #[test]
+    fn select_interfaces() {
+        let selection = Selection {
+            allow: vec!["192.168.0.0/16".parse().unwrap(), "wlan0".parse().unwrap()],
+            deny: vec!["192.168.99.0/24".parse().unwrap()],
+        };
+        assert!(selection.selects(&interface("eth0", "192.168.1.7")));
+        assert!(selection.selects(&interface("wlan0", "10.0.0.1")));
+        assert!(!selection.selects(&interface("eth1", "10.0.0.1")));
+        assert!(!selection.selects(&interface("eth0", "192.168.99.7")));
+        assert!(Selection::default().selects(&interface("tun0", "10.8.0.1")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn list_interfaces() {
+        let interfaces = list().unwrap().unwrap();
+        assert!(interfaces.iter().all(|interface| interface.index != 0));
+    }
+}
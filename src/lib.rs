@@ -1,7 +1,27 @@
+// Record types and codes are named after their DNS mnemonics.
+#![allow(clippy::upper_case_acronyms)]
+// Without the `std` feature, only the `dns` module is built.
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
+
 pub mod dns;
+#[cfg(feature = "std")]
 pub mod error;
+#[cfg(feature = "std")]
+pub mod interface;
+#[cfg(feature = "std")]
+mod netlink;
+#[cfg(feature = "std")]
+mod pktinfo;
+#[cfg(feature = "std")]
 pub mod service;
 
-pub use service::{MdnsService, Packet};
+#[cfg(feature = "std")]
+pub use error::Error;
+#[cfg(feature = "std")]
+pub use interface::{Interface, InterfaceEvent, InterfaceFilter};
+#[cfg(feature = "std")]
+pub use service::{MdnsService, MdnsServiceBuilder, Packet};
 
 pub const META_QUERY_SERVICE: &str = "_services._dns-sd._udp.local";
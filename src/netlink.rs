@@ -0,0 +1,493 @@
+//! Watches the links and addresses of the system with rtnetlink, on Linux.
+
+use std::io;
+
+use crate::interface::{Interface, InterfaceEvent};
+
+/// Returns a watcher of the interfaces, or `None` if they can't be watched on this platform.
+/// `interfaces` are those that are already up.
+#[cfg(target_os = "linux")]
+pub(crate) fn watch(interfaces: &[Interface]) -> io::Result<Option<Watcher>> {
+    Watcher::new(interfaces).map(Some)
+}
+
+/// Returns a watcher of the interfaces, or `None` if they can't be watched on this platform.
+/// `interfaces` are those that are already up.
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn watch(_: &[Interface]) -> io::Result<Option<Watcher>> {
+    Ok(None)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) enum Watcher {}
+
+#[cfg(not(target_os = "linux"))]
+impl Watcher {
+    pub async fn next(&mut self) -> io::Result<Vec<InterfaceEvent>> {
+        match *self {}
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) use linux::Watcher;
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::collections::{HashMap, HashSet};
+    use std::convert::{TryFrom, TryInto};
+    use std::ffi::CStr;
+    use std::io;
+    use std::mem;
+    use std::net::IpAddr;
+    use std::os::unix::io::{AsRawFd, FromRawFd, OwnedFd};
+
+    use tokio::io::unix::AsyncFd;
+
+    use super::{Interface, InterfaceEvent};
+    use crate::interface;
+
+    const NLMSG_HDRLEN: usize = 16;
+    const IFINFOMSG_LEN: usize = 16;
+    const IFADDRMSG_LEN: usize = 8;
+    const IFLA_IFNAME: u16 = 3;
+    const IFA_ADDRESS: u16 = 1;
+    const IFA_LOCAL: u16 = 2;
+
+    /// Rounds `len` up to the 4 bytes alignment of netlink messages and attributes.
+    fn align(len: usize) -> usize {
+        (len + 3) & !3
+    }
+
+    /// A change reported by the kernel, before it is compared to the known state.
+    #[derive(Debug, PartialEq)]
+    pub(super) enum Change {
+        Link {
+            index: u32,
+            name: Option<String>,
+            up: bool,
+        },
+        LinkRemoved {
+            index: u32,
+        },
+        Address {
+            added: bool,
+            index: u32,
+            addr: IpAddr,
+            prefix_len: u8,
+        },
+    }
+
+    /// Parses the link and address messages in `buf`. Other messages, and malformed ones, are
+    /// skipped.
+    pub(super) fn parse(mut buf: &[u8]) -> Vec<Change> {
+        let mut changes = Vec::new();
+        while buf.len() >= NLMSG_HDRLEN {
+            let len = u32::from_ne_bytes(buf[0..4].try_into().unwrap()) as usize;
+            let kind = u16::from_ne_bytes(buf[4..6].try_into().unwrap());
+            if len < NLMSG_HDRLEN || len > buf.len() {
+                break;
+            }
+            let payload = &buf[NLMSG_HDRLEN..len];
+            let change = match kind {
+                libc::RTM_NEWLINK | libc::RTM_DELLINK => parse_link(kind, payload),
+                libc::RTM_NEWADDR | libc::RTM_DELADDR => parse_address(kind, payload),
+                _ => None,
+            };
+            changes.extend(change);
+            buf = &buf[align(len).min(buf.len())..];
+        }
+        changes
+    }
+
+    fn parse_link(kind: u16, payload: &[u8]) -> Option<Change> {
+        let header = payload.get(..IFINFOMSG_LEN)?;
+        let index = u32::from_ne_bytes(header[4..8].try_into().unwrap());
+        if kind == libc::RTM_DELLINK {
+            return Some(Change::LinkRemoved { index });
+        }
+        let flags = u32::from_ne_bytes(header[8..12].try_into().unwrap());
+        let name = attributes(&payload[IFINFOMSG_LEN..])
+            .find(|(kind, _)| *kind == IFLA_IFNAME)
+            .and_then(|(_, value)| CStr::from_bytes_until_nul(value).ok())
+            .map(|name| name.to_string_lossy().into_owned());
+        Some(Change::Link {
+            index,
+            name,
+            up: flags & libc::IFF_UP as u32 != 0,
+        })
+    }
+
+    fn parse_address(kind: u16, payload: &[u8]) -> Option<Change> {
+        let header = payload.get(..IFADDRMSG_LEN)?;
+        let family = header[0] as libc::c_int;
+        let prefix_len = header[1];
+        let index = u32::from_ne_bytes(header[4..8].try_into().unwrap());
+        // on point-to-point IPv4 links, IFA_ADDRESS is the address of the peer
+        let mut addr = None;
+        for (kind, value) in attributes(&payload[IFADDRMSG_LEN..]) {
+            let value = match (family, value.len()) {
+                (libc::AF_INET, 4) => IpAddr::from(<[u8; 4]>::try_from(value).unwrap()),
+                (libc::AF_INET6, 16) => IpAddr::from(<[u8; 16]>::try_from(value).unwrap()),
+                _ => continue,
+            };
+            match kind {
+                IFA_LOCAL => addr = Some(value),
+                IFA_ADDRESS => addr = addr.or(Some(value)),
+                _ => (),
+            }
+        }
+        Some(Change::Address {
+            added: kind == libc::RTM_NEWADDR,
+            index,
+            addr: addr?,
+            prefix_len,
+        })
+    }
+
+    /// Iterates on the type and value of the route attributes in `buf`.
+    fn attributes(mut buf: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+        std::iter::from_fn(move || {
+            if buf.len() < 4 {
+                return None;
+            }
+            let len = u16::from_ne_bytes(buf[0..2].try_into().unwrap()) as usize;
+            let kind = u16::from_ne_bytes(buf[2..4].try_into().unwrap());
+            if len < 4 || len > buf.len() {
+                return None;
+            }
+            let value = &buf[4..len];
+            buf = &buf[align(len).min(buf.len())..];
+            Some((kind, value))
+        })
+    }
+
+    /// The links and addresses known to be up. The kernel repeats messages, for example when the
+    /// lifetime of an IPv6 address is renewed, and only actual changes are reported.
+    #[derive(Debug, Default)]
+    pub(super) struct State {
+        names: HashMap<u32, String>,
+        up: HashSet<u32>,
+        /// The addresses of every interface, with the length of their prefix.
+        addrs: HashMap<(u32, IpAddr), u8>,
+    }
+
+    impl State {
+        pub fn new(interfaces: &[Interface]) -> Self {
+            let mut state = State::default();
+            for interface in interfaces {
+                state.names.insert(interface.index, interface.name.clone());
+                state.up.insert(interface.index);
+                state
+                    .addrs
+                    .insert((interface.index, interface.addr), interface.prefix_len);
+            }
+            state
+        }
+
+        /// Applies `change` to the known state, and returns the events it causes.
+        pub fn apply(&mut self, change: Change) -> Vec<InterfaceEvent> {
+            match change {
+                Change::Link { index, name, up } => {
+                    if let Some(name) = name {
+                        self.names.insert(index, name);
+                    }
+                    let name = self.name(index);
+                    match (up, self.up.contains(&index)) {
+                        (true, false) => {
+                            self.up.insert(index);
+                            vec![InterfaceEvent::InterfaceUp { name, index }]
+                        }
+                        (false, true) => {
+                            self.up.remove(&index);
+                            vec![InterfaceEvent::InterfaceDown { name, index }]
+                        }
+                        _ => Vec::new(),
+                    }
+                }
+                Change::LinkRemoved { index } => {
+                    let name = self.names.remove(&index).unwrap_or_default();
+                    // the addresses of a removed link are removed without a message of their own
+                    let mut removed = Vec::new();
+                    self.addrs.retain(|&(i, addr), &mut prefix_len| {
+                        if i == index {
+                            removed.push(Interface {
+                                name: name.clone(),
+                                index,
+                                addr,
+                                prefix_len,
+                            });
+                        }
+                        i != index
+                    });
+                    removed.sort_by_key(|interface| interface.addr);
+                    let mut events: Vec<_> = removed
+                        .into_iter()
+                        .map(InterfaceEvent::AddressRemoved)
+                        .collect();
+                    if self.up.remove(&index) {
+                        events.push(InterfaceEvent::InterfaceDown { name, index });
+                    }
+                    events
+                }
+                Change::Address {
+                    added,
+                    index,
+                    addr,
+                    prefix_len,
+                } => {
+                    let changed = match added {
+                        true => self.addrs.insert((index, addr), prefix_len).is_none(),
+                        false => self.addrs.remove(&(index, addr)).is_some(),
+                    };
+                    if !changed {
+                        return Vec::new();
+                    }
+                    let interface = Interface {
+                        name: self.name(index),
+                        index,
+                        addr,
+                        prefix_len,
+                    };
+                    match added {
+                        true => vec![InterfaceEvent::AddressAdded(interface)],
+                        false => vec![InterfaceEvent::AddressRemoved(interface)],
+                    }
+                }
+            }
+        }
+
+        /// Returns the name of the interface `index`, asking the kernel if it isn't known yet.
+        fn name(&mut self, index: u32) -> String {
+            if let Some(name) = self.names.get(&index) {
+                return name.clone();
+            }
+            let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+            // SAFETY: the buffer is IF_NAMESIZE bytes long, as required.
+            let name = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+            if name.is_null() {
+                return String::new();
+            }
+            // SAFETY: on success, the buffer holds a NUL-terminated string.
+            let name = unsafe { CStr::from_ptr(buf.as_ptr()) }
+                .to_string_lossy()
+                .into_owned();
+            self.names.insert(index, name.clone());
+            name
+        }
+    }
+
+    /// A netlink socket subscribed to the changes of the links and addresses.
+    pub(crate) struct Watcher {
+        socket: AsyncFd<OwnedFd>,
+        buf: Box<[u8]>,
+        state: State,
+    }
+
+    impl Watcher {
+        pub fn new(interfaces: &[Interface]) -> io::Result<Self> {
+            // SAFETY: the arguments are valid, and the returned descriptor is checked.
+            let fd = unsafe {
+                libc::socket(
+                    libc::AF_NETLINK,
+                    libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+                    libc::NETLINK_ROUTE,
+                )
+            };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            // SAFETY: `fd` is a new descriptor, owned from now on.
+            let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+            // SAFETY: `sockaddr_nl` is valid when zeroed.
+            let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+            addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+            addr.nl_groups =
+                (libc::RTMGRP_LINK | libc::RTMGRP_IPV4_IFADDR | libc::RTMGRP_IPV6_IFADDR) as u32;
+            // SAFETY: `addr` is a `sockaddr_nl` of the given length.
+            let bound = unsafe {
+                libc::bind(
+                    fd.as_raw_fd(),
+                    &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                    mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+                )
+            };
+            if bound != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            Ok(Watcher {
+                socket: AsyncFd::new(fd)?,
+                // the kernel sends messages of up to a page, or 8KiB with large pages
+                buf: vec![0; 8192].into_boxed_slice(),
+                state: State::new(interfaces),
+            })
+        }
+
+        /// Waits for changes of the links and addresses. Messages are lost if they aren't read
+        /// quickly enough, in which case the known state is listed again, no events are returned,
+        /// and the interfaces should be listed again by the caller.
+        pub async fn next(&mut self) -> io::Result<Vec<InterfaceEvent>> {
+            loop {
+                let mut guard = self.socket.readable().await?;
+                let buf = &mut self.buf;
+                let read = guard.try_io(|socket| {
+                    // SAFETY: `buf` is valid for writes of its length.
+                    let len = unsafe {
+                        libc::recv(
+                            socket.as_raw_fd(),
+                            buf.as_mut_ptr() as *mut libc::c_void,
+                            buf.len(),
+                            0,
+                        )
+                    };
+                    match len {
+                        len if len < 0 => Err(io::Error::last_os_error()),
+                        len => Ok(len as usize),
+                    }
+                });
+                let len = match read {
+                    Ok(Err(e)) if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                        // changes were lost, so the known state is listed again
+                        self.state = State::new(&interface::list()?.unwrap_or_default());
+                        return Ok(Vec::new());
+                    }
+                    Ok(read) => read?,
+                    Err(_would_block) => continue,
+                };
+                let events: Vec<_> = parse(&self.buf[..len])
+                    .into_iter()
+                    .flat_map(|change| self.state.apply(change))
+                    .collect();
+                if !events.is_empty() {
+                    return Ok(events);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod test {
+    use super::linux::{parse, Change, State};
+    use super::*;
+
+    /// Returns a netlink message of type `kind`, with a header and route attributes.
+    fn message(kind: u16, header: &[u8], attributes: &[(u16, &[u8])]) -> Vec<u8> {
+        let mut payload = header.to_vec();
+        for (kind, value) in attributes {
+            payload.extend_from_slice(&(4 + value.len() as u16).to_ne_bytes());
+            payload.extend_from_slice(&kind.to_ne_bytes());
+            payload.extend_from_slice(value);
+            payload.resize((payload.len() + 3) & !3, 0);
+        }
+        let mut message = Vec::new();
+        message.extend_from_slice(&(16 + payload.len() as u32).to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(&[0; 10]);
+        message.extend_from_slice(&payload);
+        message
+    }
+
+    fn link(kind: u16, index: u32, flags: u32, name: &str) -> Vec<u8> {
+        let mut header = vec![0; 4];
+        header.extend_from_slice(&index.to_ne_bytes());
+        header.extend_from_slice(&flags.to_ne_bytes());
+        header.extend_from_slice(&[0; 4]);
+        message(kind, &header, &[(3, format!("{}\0", name).as_bytes())])
+    }
+
+    fn address(kind: u16, index: u32, addr: [u8; 4], prefix_len: u8) -> Vec<u8> {
+        let mut header = vec![libc::AF_INET as u8, prefix_len, 0, 0];
+        header.extend_from_slice(&index.to_ne_bytes());
+        message(kind, &header, &[(1, &[10, 0, 0, 1]), (2, &addr)])
+    }
+
+    #[test]
+    fn parse_messages() {
+        let mut buf = link(libc::RTM_NEWLINK, 7, libc::IFF_UP as u32, "wlan0");
+        buf.extend(address(libc::RTM_NEWADDR, 7, [192, 168, 1, 7], 24));
+        buf.extend(link(libc::RTM_DELLINK, 7, 0, "wlan0"));
+        assert_eq!(
+            parse(&buf),
+            vec![
+                Change::Link {
+                    index: 7,
+                    name: Some("wlan0".to_string()),
+                    up: true,
+                },
+                Change::Address {
+                    added: true,
+                    index: 7,
+                    addr: "192.168.1.7".parse().unwrap(),
+                    prefix_len: 24,
+                },
+                Change::LinkRemoved { index: 7 },
+            ]
+        );
+        // a truncated message is skipped
+        assert_eq!(parse(&buf[..20]), vec![]);
+    }
+
+    #[test]
+    fn report_changes_once() {
+        let mut state = State::new(&[]);
+        let mut events = Vec::new();
+        let mut buf = link(libc::RTM_NEWLINK, 7, libc::IFF_UP as u32, "wlan0");
+        buf.extend(link(libc::RTM_NEWLINK, 7, libc::IFF_UP as u32, "wlan0"));
+        buf.extend(address(libc::RTM_NEWADDR, 7, [192, 168, 1, 7], 24));
+        buf.extend(address(libc::RTM_NEWADDR, 7, [192, 168, 1, 7], 24));
+        buf.extend(address(libc::RTM_DELADDR, 7, [192, 168, 1, 7], 24));
+        buf.extend(link(libc::RTM_NEWLINK, 7, 0, "wlan0"));
+        for change in parse(&buf) {
+            events.extend(state.apply(change));
+        }
+
+        let wlan = Interface {
+            name: "wlan0".to_string(),
+            index: 7,
+            addr: "192.168.1.7".parse().unwrap(),
+            prefix_len: 24,
+        };
+        assert_eq!(
+            events,
+            vec![
+                InterfaceEvent::InterfaceUp {
+                    name: "wlan0".to_string(),
+                    index: 7
+                },
+                InterfaceEvent::AddressAdded(wlan.clone()),
+                InterfaceEvent::AddressRemoved(wlan),
+                InterfaceEvent::InterfaceDown {
+                    name: "wlan0".to_string(),
+                    index: 7
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_addresses_of_removed_link() {
+        let wlan = Interface {
+            name: "wlan0".to_string(),
+            index: 7,
+            addr: "192.168.1.7".parse().unwrap(),
+            prefix_len: 24,
+        };
+        let mut state = State::new(std::slice::from_ref(&wlan));
+        let events = parse(&link(libc::RTM_DELLINK, 7, 0, "wlan0"))
+            .into_iter()
+            .flat_map(|change| state.apply(change))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            events,
+            vec![
+                InterfaceEvent::AddressRemoved(wlan),
+                InterfaceEvent::InterfaceDown {
+                    name: "wlan0".to_string(),
+                    index: 7
+                },
+            ]
+        );
+    }
+}
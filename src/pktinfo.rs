@@ -0,0 +1,171 @@
+//! Receives packets along with the interface they arrived on, their destination address and their
+//! hop limit, from the IP_PKTINFO, IPV6_PKTINFO, IP_TTL and IPV6_HOPLIMIT control messages.
+
+use std::io;
+use std::net::SocketAddr;
+
+use tokio::net::UdpSocket;
+
+use crate::service::PacketInfo;
+
+/// Asks the kernel to attach the packet info to the packets received on the IPv4 `socket`. It is
+/// a no-op on platforms where it isn't supported.
+#[cfg(target_os = "linux")]
+pub(crate) fn enable_v4(socket: &std::net::UdpSocket) -> io::Result<()> {
+    linux::set_option(socket, libc::IPPROTO_IP, libc::IP_PKTINFO)?;
+    linux::set_option(socket, libc::IPPROTO_IP, libc::IP_RECVTTL)
+}
+
+/// Asks the kernel to attach the packet info to the packets received on the IPv6 `socket`. It is
+/// a no-op on platforms where it isn't supported.
+#[cfg(target_os = "linux")]
+pub(crate) fn enable_v6(socket: &std::net::UdpSocket) -> io::Result<()> {
+    linux::set_option(socket, libc::IPPROTO_IPV6, libc::IPV6_RECVPKTINFO)?;
+    linux::set_option(socket, libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT)
+}
+
+/// Receives a packet on `socket`, with the packet info enabled by `enable_v4` or `enable_v6`.
+#[cfg(target_os = "linux")]
+pub(crate) async fn recv_from(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, PacketInfo)> {
+    socket
+        .async_io(tokio::io::Interest::READABLE, || {
+            linux::recvmsg(socket, buf)
+        })
+        .await
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn enable_v4(_: &std::net::UdpSocket) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) fn enable_v6(_: &std::net::UdpSocket) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub(crate) async fn recv_from(
+    socket: &UdpSocket,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, PacketInfo)> {
+    let (len, from) = socket.recv_from(buf).await?;
+    Ok((len, from, PacketInfo::default()))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::io;
+    use std::mem;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+    use std::os::unix::io::AsRawFd;
+    use std::ptr;
+
+    use crate::service::PacketInfo;
+
+    pub fn set_option(
+        socket: &impl AsRawFd,
+        level: libc::c_int,
+        name: libc::c_int,
+    ) -> io::Result<()> {
+        let enabled: libc::c_int = 1;
+        // SAFETY: the option value is a `c_int` of the given length.
+        let result = unsafe {
+            libc::setsockopt(
+                socket.as_raw_fd(),
+                level,
+                name,
+                &enabled as *const libc::c_int as *const libc::c_void,
+                mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        match result {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+
+    pub fn recvmsg(
+        socket: &impl AsRawFd,
+        buf: &mut [u8],
+    ) -> io::Result<(usize, SocketAddr, PacketInfo)> {
+        // room for the packet info and the hop limit of either family, aligned as `cmsghdr`
+        let mut control = [0u64; 16];
+        // SAFETY: `sockaddr_storage` and `msghdr` are valid when zeroed.
+        let mut from: libc::sockaddr_storage = unsafe { mem::zeroed() };
+        let mut iov = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_name = &mut from as *mut libc::sockaddr_storage as *mut libc::c_void;
+        msg.msg_namelen = mem::size_of::<libc::sockaddr_storage>() as libc::socklen_t;
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+
+        // SAFETY: the buffers pointed to by `msg` outlive the call, with the given lengths.
+        let len = unsafe { libc::recvmsg(socket.as_raw_fd(), &mut msg, 0) };
+        if len < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: the kernel wrote an address of the family it tells.
+        let from = unsafe { socket_addr(&from) }.ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "unsupported address family")
+        })?;
+
+        let mut info = PacketInfo::default();
+        // SAFETY: the control messages were written by the kernel, within `msg_controllen`, and
+        // their data is read as the structure matching their level and type.
+        unsafe {
+            let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+            while !cmsg.is_null() {
+                let data = libc::CMSG_DATA(cmsg);
+                match ((*cmsg).cmsg_level, (*cmsg).cmsg_type) {
+                    (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                        let pktinfo = ptr::read_unaligned(data as *const libc::in_pktinfo);
+                        info.interface = Some(pktinfo.ipi_ifindex as u32);
+                        let destination = u32::from_be(pktinfo.ipi_addr.s_addr);
+                        info.destination = Some(IpAddr::V4(Ipv4Addr::from(destination)));
+                    }
+                    (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                        let pktinfo = ptr::read_unaligned(data as *const libc::in6_pktinfo);
+                        info.interface = Some(pktinfo.ipi6_ifindex);
+                        let destination = Ipv6Addr::from(pktinfo.ipi6_addr.s6_addr);
+                        info.destination = Some(IpAddr::V6(destination));
+                    }
+                    (libc::IPPROTO_IP, libc::IP_TTL)
+                    | (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                        let hop_limit = ptr::read_unaligned(data as *const libc::c_int);
+                        info.hop_limit = Some(hop_limit as u8);
+                    }
+                    _ => (),
+                }
+                cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+            }
+        }
+        Ok((len as usize, from, info))
+    }
+
+    /// Converts an IPv4 or IPv6 address written by the kernel.
+    unsafe fn socket_addr(addr: &libc::sockaddr_storage) -> Option<SocketAddr> {
+        match addr.ss_family as libc::c_int {
+            libc::AF_INET => {
+                let addr = &*(addr as *const libc::sockaddr_storage as *const libc::sockaddr_in);
+                let ip = Ipv4Addr::from(u32::from_be(addr.sin_addr.s_addr));
+                Some(SocketAddrV4::new(ip, u16::from_be(addr.sin_port)).into())
+            }
+            libc::AF_INET6 => {
+                let addr = &*(addr as *const libc::sockaddr_storage as *const libc::sockaddr_in6);
+                let ip = Ipv6Addr::from(addr.sin6_addr.s6_addr);
+                let port = u16::from_be(addr.sin6_port);
+                Some(SocketAddrV6::new(ip, port, addr.sin6_flowinfo, addr.sin6_scope_id).into())
+            }
+            _ => None,
+        }
+    }
+}
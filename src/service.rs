@@ -1,31 +1,40 @@
-use std::collections::HashSet;
+use std::collections::{HashSet, VecDeque};
+use std::future;
 use std::io;
 use std::net::SocketAddr;
-use std::net::{Ipv4Addr, Ipv6Addr};
-use std::str::FromStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 use std::time::Duration;
 
 use crate::dns;
-use crate::error::Error;
+use crate::error::{Context, Error};
+use crate::interface::{self, Interface, InterfaceEvent, InterfaceFilter, Selection};
+use crate::netlink::{self, Watcher};
+use crate::pktinfo;
 use crate::META_QUERY_SERVICE;
 
-use super::dns::{QueryClass, QueryType};
-use once_cell::sync::Lazy;
+use super::dns::{Name, QueryClass, QueryType};
+use net2::UdpSocketExt;
+use socket2::SockRef;
+use tokio::net::UdpSocket;
 use tokio::sync::{mpsc, oneshot};
 use tokio::time;
 
-static IPV4_MDNS_MULTICAST_ADDRESS: Lazy<SocketAddr> =
-    Lazy::new(|| SocketAddr::from((Ipv4Addr::new(224, 0, 0, 251), 5353)));
-static IPV6_MDNS_MULTICAST_ADDRESS: Lazy<SocketAddr> =
-    Lazy::new(|| SocketAddr::from((Ipv6Addr::from_str("FF02::FB").unwrap(), 5353)));
+/// The mDNS port, as defined by RFC 6762 §3.
+const MDNS_PORT: u16 = 5353;
+const IPV4_MDNS_MULTICAST_GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const IPV6_MDNS_MULTICAST_GROUP: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb);
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Query {
-    pub name: String,
+    pub name: Name<'static>,
     pub prefer_unicast: bool,
+    #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::qtype"))]
     pub qtype: QueryType,
+    #[cfg_attr(feature = "serde", serde(with = "crate::dns::serialization::qclass"))]
     pub qclass: QueryClass,
     pub from: SocketAddr,
+    pub info: PacketInfo,
     pub id: u16,
 }
 
@@ -36,54 +45,237 @@ impl Query {
 }
 
 #[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Response {
+    pub message: dns::Message,
+    pub from: SocketAddr,
+    pub info: PacketInfo,
+}
+
+/// How a packet was received. The fields are `None` on platforms where they aren't reported, and
+/// always set on Linux.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PacketInfo {
+    /// The index of the interface the packet arrived on, as in `Interface::index`.
+    pub interface: Option<u32>,
+    /// The destination address of the packet: the multicast group, or an address of the
+    /// interface if the packet was sent by unicast.
+    pub destination: Option<IpAddr>,
+    /// The TTL of an IPv4 packet, or the hop limit of an IPv6 one. RFC 6762 §11 requires 255 on
+    /// link-local traffic, as it can't have been routed from another network.
+    pub hop_limit: Option<u8>,
+}
+
+impl PacketInfo {
+    /// Returns whether the packet was sent to a multicast group. Returns false if the destination
+    /// isn't known.
+    pub fn is_multicast(&self) -> bool {
+        self.destination.is_some_and(|addr| addr.is_multicast())
+    }
+}
+
+#[derive(Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Packet {
     Query(Vec<Query>),
-    Response(mdns::Response),
+    Response(Response),
+    /// An interface or an address came or went. The multicast groups were joined or left
+    /// accordingly.
+    Interface(InterfaceEvent),
 }
 
 pub struct MdnsService {
-    socket_v4: tokio::net::UdpSocket,
-    socket_v6: tokio::net::UdpSocket,
-    query_socket: tokio::net::UdpSocket,
-    recv_buffer_v4: [u8; 2048],
-    recv_buffer_v6: [u8; 2048],
+    socket_v4: Option<UdpSocket>,
+    socket_v6: Option<UdpSocket>,
+    query_socket: UdpSocket,
+    recv_buffer_v4: Box<[u8]>,
+    recv_buffer_v6: Box<[u8]>,
+    multicast_addr_v4: SocketAddr,
+    multicast_addr_v6: SocketAddr,
+    multicast_group_v4: Ipv4Addr,
+    multicast_group_v6: Ipv6Addr,
+    selection: Selection,
+    /// The selected interfaces.
+    interfaces: Vec<Interface>,
+    /// The interfaces the multicast groups are joined on, and packets are sent on.
+    multicast_ifs: MulticastInterfaces,
+    watcher: Option<Watcher>,
+    /// Interface events pending to be returned by `next`.
+    interface_events: VecDeque<InterfaceEvent>,
     /// Buffers pending to send on the main socket.
     send_buffers: Vec<Vec<u8>>,
     /// Buffers pending to send on the query socket.
     query_send_buffers: Vec<Vec<u8>>,
-    advertized_sevices: HashSet<String>,
-    discovery_scheduler_snd: mpsc::Sender<String>,
-    discovery_scheduler_rcv: mpsc::Receiver<String>,
+    advertized_sevices: HashSet<Name<'static>>,
+    discovery_scheduler_snd: mpsc::Sender<Name<'static>>,
+    discovery_scheduler_rcv: mpsc::Receiver<Name<'static>>,
 }
 
-pub struct ServiceDiscovery(oneshot::Sender<()>, String);
+/// The indexes of the interfaces a multicast group was joined on, with the address the IPv4 group
+/// was joined with. The default interface of the system is `0` and `0.0.0.0`.
+#[derive(Debug, Default)]
+struct MulticastInterfaces {
+    v4: Vec<(u32, Ipv4Addr)>,
+    v6: Vec<u32>,
+}
+
+/// Handle to a service discovery. The discovery stops when the handle is dropped.
+pub struct ServiceDiscovery(#[allow(dead_code)] oneshot::Sender<()>, Name<'static>);
 
 impl ServiceDiscovery {
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> &Name<'static> {
         &self.1
     }
 }
 
-macro_rules! send_packets {
-    ($self:ident, $socket:ident, $addr:expr, $to_send:ident) => {
-        match $self.$socket.send_to(&$to_send, $addr).await {
-            Ok(bytes_written) => {
-                debug_assert_eq!(bytes_written, $to_send.len());
-            }
-            Err(_) => {
-                // Errors are non-fatal because they can happen for example if we lose
-                // connection to the network.
-                $self.send_buffers.clear();
-                break;
-            }
+/// Builder for a `MdnsService`, returned by `MdnsService::builder`.
+///
+/// The defaults are those of RFC 6762: port 5353, groups 224.0.0.251 and FF02::FB, and a TTL or
+/// hop limit of 255 on multicast packets. Setting another port or group makes it possible to run several
+/// services on the same machine without seeing each other's traffic, for example in tests.
+///
+/// The groups are joined on every interface that is up and supports multicast, unless some are
+/// allowed or denied with `allow_interface` and `deny_interface`.
+#[derive(Debug, Clone)]
+pub struct MdnsServiceBuilder {
+    port: u16,
+    multicast_group_v4: Ipv4Addr,
+    multicast_group_v6: Ipv6Addr,
+    bind_address_v4: Ipv4Addr,
+    bind_address_v6: Ipv6Addr,
+    ipv4: bool,
+    ipv6: bool,
+    multicast_ttl_v4: u32,
+    multicast_hops_v6: u32,
+    recv_buffer_size: usize,
+    channel_capacity: usize,
+    loopback: bool,
+    interfaces: Selection,
+    watch_interfaces: bool,
+}
+
+impl Default for MdnsServiceBuilder {
+    fn default() -> Self {
+        Self {
+            port: MDNS_PORT,
+            multicast_group_v4: IPV4_MDNS_MULTICAST_GROUP,
+            multicast_group_v6: IPV6_MDNS_MULTICAST_GROUP,
+            bind_address_v4: Ipv4Addr::UNSPECIFIED,
+            bind_address_v6: Ipv6Addr::UNSPECIFIED,
+            ipv4: true,
+            ipv6: true,
+            multicast_ttl_v4: 255,
+            multicast_hops_v6: 255,
+            recv_buffer_size: 2048,
+            channel_capacity: 100,
+            loopback: false,
+            interfaces: Selection::default(),
+            watch_interfaces: true,
         }
-    };
+    }
 }
 
-impl MdnsService {
-    /// creates a new mdns Service to advertize and discover mdns services. If `loopback` is
-    /// enabled, you will receive the multicast packets.
-    pub fn new(loopback: bool) -> Result<Self, Error> {
+impl MdnsServiceBuilder {
+    /// Sets the port the service listens on, and sends multicast packets to. Defaults to 5353.
+    pub fn set_port(&mut self, port: u16) -> &mut Self {
+        self.port = port;
+        self
+    }
+
+    /// Sets the IPv4 multicast group. Defaults to 224.0.0.251.
+    pub fn set_multicast_group_v4(&mut self, group: Ipv4Addr) -> &mut Self {
+        self.multicast_group_v4 = group;
+        self
+    }
+
+    /// Sets the IPv6 multicast group. Defaults to FF02::FB.
+    pub fn set_multicast_group_v6(&mut self, group: Ipv6Addr) -> &mut Self {
+        self.multicast_group_v6 = group;
+        self
+    }
+
+    /// Sets the address the IPv4 sockets are bound to. Defaults to 0.0.0.0.
+    pub fn set_bind_address_v4(&mut self, address: Ipv4Addr) -> &mut Self {
+        self.bind_address_v4 = address;
+        self
+    }
+
+    /// Sets the address the IPv6 sockets are bound to. Defaults to ::.
+    pub fn set_bind_address_v6(&mut self, address: Ipv6Addr) -> &mut Self {
+        self.bind_address_v6 = address;
+        self
+    }
+
+    /// Enables or disables IPv4. Defaults to true.
+    pub fn set_ipv4(&mut self, enabled: bool) -> &mut Self {
+        self.ipv4 = enabled;
+        self
+    }
+
+    /// Enables or disables IPv6. Defaults to true.
+    pub fn set_ipv6(&mut self, enabled: bool) -> &mut Self {
+        self.ipv6 = enabled;
+        self
+    }
+
+    /// Sets the TTL of multicast IPv4 packets. Defaults to 255.
+    pub fn set_multicast_ttl_v4(&mut self, ttl: u32) -> &mut Self {
+        self.multicast_ttl_v4 = ttl;
+        self
+    }
+
+    /// Sets the hop limit of multicast IPv6 packets. Defaults to 255.
+    pub fn set_multicast_hops_v6(&mut self, hops: u32) -> &mut Self {
+        self.multicast_hops_v6 = hops;
+        self
+    }
+
+    /// Sets the size of the buffers packets are received in. Longer packets are truncated. Must
+    /// be positive, and defaults to 2048 bytes.
+    pub fn set_recv_buffer_size(&mut self, size: usize) -> &mut Self {
+        self.recv_buffer_size = size;
+        self
+    }
+
+    /// Sets the number of discovery queries that can be pending before they are sent. Must be
+    /// positive, and defaults to 100.
+    pub fn set_channel_capacity(&mut self, capacity: usize) -> &mut Self {
+        self.channel_capacity = capacity;
+        self
+    }
+
+    /// Enables or disables the reception of the multicast packets sent by the service itself.
+    /// Defaults to false.
+    pub fn set_loopback(&mut self, enabled: bool) -> &mut Self {
+        self.loopback = enabled;
+        self
+    }
+
+    /// Allows the interfaces matching `filter`. Once an interface is allowed, the service only
+    /// runs on the allowed interfaces.
+    pub fn allow_interface(&mut self, filter: InterfaceFilter) -> &mut Self {
+        self.interfaces.allow.push(filter);
+        self
+    }
+
+    /// Denies the interfaces matching `filter`, for example `docker*` or `10.8.0.0/16`, even if
+    /// they are allowed.
+    pub fn deny_interface(&mut self, filter: InterfaceFilter) -> &mut Self {
+        self.interfaces.deny.push(filter);
+        self
+    }
+
+    /// Enables or disables following the changes of the interfaces, to join and leave the
+    /// multicast groups as interfaces and addresses come and go. The changes are returned by
+    /// `MdnsService::next`. Only supported on Linux, with rtnetlink. Defaults to true.
+    pub fn set_watch_interfaces(&mut self, enabled: bool) -> &mut Self {
+        self.watch_interfaces = enabled;
+        self
+    }
+
+    /// Binds the sockets, joins the multicast groups, and returns the service.
+    pub fn build(&self) -> Result<MdnsService, Error> {
         #[cfg(unix)]
         fn platform_specific(s: &net2::UdpBuilder) -> io::Result<()> {
             net2::unix::UnixUdpBuilderExt::reuse_port(s, true)?;
@@ -94,75 +286,392 @@ impl MdnsService {
             Ok(())
         }
 
+        fn bind(
+            builder: io::Result<net2::UdpBuilder>,
+            addr: SocketAddr,
+        ) -> Result<std::net::UdpSocket, Error> {
+            let builder = builder.context("create socket")?;
+            builder.reuse_address(true).context("set SO_REUSEADDR")?;
+            platform_specific(&builder).context("set SO_REUSEPORT")?;
+            let socket = builder
+                .bind(addr)
+                .map_err(|source| Error::Bind { addr, source })?;
+            socket.set_nonblocking(true).context("set O_NONBLOCK")?;
+            Ok(socket)
+        }
+
+        if !self.ipv4 && !self.ipv6 {
+            return Err(Error::InvalidConfig("IPv4 and IPv6 are both disabled"));
+        }
+        if self.recv_buffer_size == 0 {
+            return Err(Error::InvalidConfig("receive buffer size must be positive"));
+        }
+        if self.channel_capacity == 0 {
+            return Err(Error::InvalidConfig("channel capacity must be positive"));
+        }
+
+        let interfaces = interface::list().context("list interfaces")?;
+        if interfaces.is_none() && !self.interfaces.is_empty() {
+            return Err(Error::InvalidConfig(
+                "interfaces can't be listed on this platform",
+            ));
+        }
+
         // setup ipv4 socket
-        let std_socket_v4 = {
-            let builder = net2::UdpBuilder::new_v4()?;
-            builder.reuse_address(true)?;
-            platform_specific(&builder)?;
-            builder.bind(("0.0.0.0", 5353))?
+        let socket_v4 = if self.ipv4 {
+            let socket = bind(
+                net2::UdpBuilder::new_v4(),
+                SocketAddr::from((self.bind_address_v4, self.port)),
+            )?;
+            socket
+                .set_multicast_loop_v4(self.loopback)
+                .context("set IP_MULTICAST_LOOP")?;
+            socket
+                .set_multicast_ttl_v4(self.multicast_ttl_v4)
+                .context("set IP_MULTICAST_TTL")?;
+            pktinfo::enable_v4(&socket).context("set IP_PKTINFO")?;
+            Some(UdpSocket::from_std(socket).context("register socket")?)
+        } else {
+            None
         };
 
-        let socket_v4 = tokio::net::UdpSocket::from_std(std_socket_v4)?;
-        socket_v4.set_multicast_loop_v4(loopback)?;
-        socket_v4.set_multicast_ttl_v4(255)?;
-        socket_v4.join_multicast_v4(From::from([224, 0, 0, 251]), Ipv4Addr::UNSPECIFIED)?;
-
         // setup ipv6 socket
-        let std_socket_v6 = {
-            let builder = net2::UdpBuilder::new_v6()?;
-            builder.reuse_address(true)?;
-            platform_specific(&builder)?;
-            builder.bind(("::", 5353))?
+        let socket_v6 = if self.ipv6 {
+            let socket = bind(
+                net2::UdpBuilder::new_v6(),
+                SocketAddr::from((self.bind_address_v6, self.port)),
+            )?;
+            socket
+                .set_multicast_loop_v6(self.loopback)
+                .context("set IPV6_MULTICAST_LOOP")?;
+            pktinfo::enable_v6(&socket).context("set IPV6_RECVPKTINFO")?;
+            socket
+                .set_multicast_hops_v6(self.multicast_hops_v6)
+                .context("set IPV6_MULTICAST_HOPS")?;
+            Some(UdpSocket::from_std(socket).context("register socket")?)
+        } else {
+            None
         };
 
-        let socket_v6 = tokio::net::UdpSocket::from_std(std_socket_v6)?;
-        socket_v6.set_multicast_loop_v6(loopback)?;
-        socket_v6.join_multicast_v6(&FromStr::from_str("FF02::FB").unwrap(), 0)?;
-
-        let query_socket = tokio::net::UdpSocket::from_std(std::net::UdpSocket::bind(
-            &[
-                SocketAddr::from((Ipv4Addr::from([0u8, 0, 0, 0]), 0u16)),
-                SocketAddr::from((Ipv6Addr::from_str("::").unwrap(), 0u16)),
-            ][..],
-        )?)?;
+        // the IPv6 address is only used if the IPv4 one can't be bound
+        let mut query_addrs = Vec::new();
+        if self.ipv4 {
+            query_addrs.push(SocketAddr::from((self.bind_address_v4, 0)));
+        }
+        if self.ipv6 {
+            query_addrs.push(SocketAddr::from((self.bind_address_v6, 0)));
+        }
+        let query_socket =
+            std::net::UdpSocket::bind(&query_addrs[..]).map_err(|source| Error::Bind {
+                addr: query_addrs[0],
+                source,
+            })?;
+        query_socket
+            .set_nonblocking(true)
+            .context("set O_NONBLOCK")?;
+        // queries are multicast too, and must not be mistaken for routed packets
+        if self.ipv4 {
+            query_socket
+                .set_multicast_ttl_v4(self.multicast_ttl_v4)
+                .context("set IP_MULTICAST_TTL")?;
+        } else {
+            query_socket
+                .set_multicast_hops_v6(self.multicast_hops_v6)
+                .context("set IPV6_MULTICAST_HOPS")?;
+        }
+        let query_socket = UdpSocket::from_std(query_socket).context("register socket")?;
 
-        let (tx, rx) = mpsc::channel(100);
+        let (tx, rx) = mpsc::channel(self.channel_capacity);
 
-        Ok(MdnsService {
+        let mut service = MdnsService {
             socket_v4,
             socket_v6,
             query_socket,
-            recv_buffer_v4: [0; 2048],
-            recv_buffer_v6: [0; 2048],
+            recv_buffer_v4: vec![0; self.recv_buffer_size].into_boxed_slice(),
+            recv_buffer_v6: vec![0; self.recv_buffer_size].into_boxed_slice(),
+            multicast_addr_v4: SocketAddr::from((self.multicast_group_v4, self.port)),
+            multicast_addr_v6: SocketAddr::from((self.multicast_group_v6, self.port)),
+            multicast_group_v4: self.multicast_group_v4,
+            multicast_group_v6: self.multicast_group_v6,
+            selection: self.interfaces.clone(),
+            interfaces: Vec::new(),
+            multicast_ifs: MulticastInterfaces::default(),
+            watcher: None,
+            interface_events: VecDeque::new(),
             send_buffers: Vec::new(),
             query_send_buffers: Vec::new(),
             advertized_sevices: HashSet::new(),
             discovery_scheduler_snd: tx,
             discovery_scheduler_rcv: rx,
-        })
+        };
+
+        match interfaces {
+            Some(interfaces) => {
+                service.update_memberships(&interfaces)?;
+                if self.watch_interfaces {
+                    service.watcher = netlink::watch(&interfaces).context("watch interfaces")?;
+                }
+            }
+            // without a list of the interfaces, the groups are joined on the default one
+            None => {
+                if let Some(socket) = &service.socket_v4 {
+                    socket
+                        .join_multicast_v4(self.multicast_group_v4, Ipv4Addr::UNSPECIFIED)
+                        .map_err(|source| Error::JoinMulticast {
+                            group: self.multicast_group_v4.into(),
+                            source,
+                        })?;
+                    service.multicast_ifs.v4.push((0, Ipv4Addr::UNSPECIFIED));
+                }
+                if let Some(socket) = &service.socket_v6 {
+                    socket
+                        .join_multicast_v6(&self.multicast_group_v6, 0)
+                        .map_err(|source| Error::JoinMulticast {
+                            group: self.multicast_group_v6.into(),
+                            source,
+                        })?;
+                    service.multicast_ifs.v6.push(0);
+                }
+            }
+        }
+        Ok(service)
+    }
+}
+
+macro_rules! send_packets {
+    ($self:ident, $socket:expr, $addr:expr, $to_send:ident) => {
+        match send_multicast(&$socket, $addr, &$self.multicast_ifs, &$to_send).await {
+            Ok(()) => (),
+            Err(_) => {
+                // Errors are non-fatal because they can happen for example if we lose
+                // connection to the network.
+                $self.send_buffers.clear();
+                break;
+            }
+        }
+    };
+}
+
+/// Sends `buf` to the multicast `addr` on each interface the group was joined on. Only fails if
+/// it couldn't be sent on any of them.
+async fn send_multicast(
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    interfaces: &MulticastInterfaces,
+    buf: &[u8],
+) -> io::Result<()> {
+    let count = match addr {
+        SocketAddr::V4(_) => interfaces.v4.len(),
+        SocketAddr::V6(_) => interfaces.v6.len(),
+    };
+    let mut sent = false;
+    let mut error = None;
+    for i in 0..count {
+        let set_interface = match addr {
+            SocketAddr::V4(_) => SockRef::from(socket).set_multicast_if_v4(&interfaces.v4[i].1),
+            SocketAddr::V6(_) => SockRef::from(socket).set_multicast_if_v6(interfaces.v6[i]),
+        };
+        if let Err(e) = set_interface {
+            error = Some(e);
+            continue;
+        }
+        match socket.send_to(buf, addr).await {
+            Ok(bytes_written) => {
+                debug_assert_eq!(bytes_written, buf.len());
+                sent = true;
+            }
+            Err(e) => error = Some(e),
+        }
+    }
+    match error {
+        Some(error) if !sent => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Joins the IPv4 `group` on the interfaces of `selected` it wasn't joined on yet, and leaves it on
+/// the others. `joined` is updated with the new memberships. Only fails if the group couldn't be
+/// joined on any interface.
+fn update_memberships_v4(
+    socket: &UdpSocket,
+    group: Ipv4Addr,
+    selected: &[Interface],
+    joined: &mut Vec<(u32, Ipv4Addr)>,
+) -> io::Result<()> {
+    let mut memberships: Vec<(u32, Ipv4Addr)> = Vec::new();
+    let mut error = None;
+    for interface in selected {
+        let addr = match interface.addr {
+            IpAddr::V4(addr) => addr,
+            IpAddr::V6(_) => continue,
+        };
+        // the group is joined once per interface, even if it has several addresses
+        if memberships
+            .iter()
+            .any(|&(index, _)| index == interface.index)
+        {
+            continue;
+        }
+        let previous = joined.iter().find(|&&(index, _)| index == interface.index);
+        match previous {
+            // the membership is kept while the address it was joined with is there
+            Some(&(index, joined_with))
+                if selected
+                    .iter()
+                    .any(|i| i.index == index && i.addr == IpAddr::V4(joined_with)) =>
+            {
+                memberships.push((index, joined_with));
+            }
+            _ => {
+                if let Some(&(_, previous)) = previous {
+                    let _ = socket.leave_multicast_v4(group, previous);
+                }
+                match socket.join_multicast_v4(group, addr) {
+                    Ok(()) => memberships.push((interface.index, addr)),
+                    Err(e) => error = Some(e),
+                }
+            }
+        }
+    }
+    for &(index, addr) in joined.iter() {
+        if !memberships.iter().any(|&(i, _)| i == index) {
+            // the interface may be gone already, along with the membership
+            let _ = socket.leave_multicast_v4(group, addr);
+        }
+    }
+    *joined = memberships;
+    match error {
+        Some(error) if joined.is_empty() => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Joins the IPv6 `group` on the interfaces of `selected` it wasn't joined on yet, and leaves it on
+/// the others. `joined` is updated with the new memberships. Only fails if the group couldn't be
+/// joined on any interface.
+fn update_memberships_v6(
+    socket: &UdpSocket,
+    group: &Ipv6Addr,
+    selected: &[Interface],
+    joined: &mut Vec<u32>,
+) -> io::Result<()> {
+    let mut memberships = Vec::new();
+    let mut error = None;
+    for interface in selected {
+        if interface.addr.is_ipv4() || memberships.contains(&interface.index) {
+            continue;
+        }
+        if joined.contains(&interface.index) {
+            memberships.push(interface.index);
+            continue;
+        }
+        match socket.join_multicast_v6(group, interface.index) {
+            Ok(()) => memberships.push(interface.index),
+            Err(e) => error = Some(e),
+        }
+    }
+    for &index in joined.iter() {
+        if !memberships.contains(&index) {
+            // the interface may be gone already, along with the membership
+            let _ = socket.leave_multicast_v6(group, index);
+        }
+    }
+    *joined = memberships;
+    match error {
+        Some(error) if joined.is_empty() => Err(error),
+        _ => Ok(()),
+    }
+}
+
+/// Waits for changes of the interfaces, or forever if they aren't watched.
+async fn watch(watcher: &mut Option<Watcher>) -> io::Result<Vec<InterfaceEvent>> {
+    match watcher {
+        Some(watcher) => watcher.next().await,
+        None => future::pending().await,
+    }
+}
+
+/// Receives a packet on `socket`, or waits forever if the address family is disabled.
+async fn recv_from(
+    socket: &Option<UdpSocket>,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr, PacketInfo)> {
+    match socket {
+        Some(socket) => pktinfo::recv_from(socket, buf).await,
+        None => future::pending().await,
+    }
+}
+
+impl MdnsService {
+    /// creates a new mdns Service to advertize and discover mdns services. If `loopback` is
+    /// enabled, you will receive the multicast packets.
+    ///
+    /// See `builder` to configure the service further.
+    pub fn new(loopback: bool) -> Result<Self, Error> {
+        Self::builder().set_loopback(loopback).build()
+    }
+
+    /// Returns a builder to configure the sockets and protocol parameters of the service.
+    pub fn builder() -> MdnsServiceBuilder {
+        MdnsServiceBuilder::default()
     }
 
-    /// register a service to advertize
-    pub fn register(&mut self, svc: &str) {
-        self.advertized_sevices.insert(svc.to_string());
+    /// Returns the addresses of the interfaces the multicast groups were joined on. It is empty if
+    /// interfaces can't be listed on this platform, and the default interface is used.
+    pub fn interfaces(&self) -> &[Interface] {
+        &self.interfaces
+    }
+
+    /// Joins the multicast groups on the selected `interfaces`, and leaves them on the interfaces
+    /// that are gone or no longer selected.
+    fn update_memberships(&mut self, interfaces: &[Interface]) -> Result<(), Error> {
+        self.interfaces = interfaces
+            .iter()
+            .filter(|interface| self.selection.selects(interface))
+            .cloned()
+            .collect();
+        let mut result = Ok(());
+        if let Some(socket) = &self.socket_v4 {
+            let group = self.multicast_group_v4;
+            let joined = &mut self.multicast_ifs.v4;
+            if let Err(source) = update_memberships_v4(socket, group, &self.interfaces, joined) {
+                let group = group.into();
+                result = Err(Error::JoinMulticast { group, source });
+            }
+        }
+        if let Some(socket) = &self.socket_v6 {
+            let group = &self.multicast_group_v6;
+            let joined = &mut self.multicast_ifs.v6;
+            if let Err(source) = update_memberships_v6(socket, group, &self.interfaces, joined) {
+                let group = (*group).into();
+                result = Err(Error::JoinMulticast { group, source });
+            }
+        }
+        result
+    }
+
+    /// register a service to advertize. Service names are matched case-insensitively.
+    pub fn register<'a>(&mut self, svc: impl Into<Name<'a>>) {
+        self.advertized_sevices.insert(svc.into().into_owned());
     }
 
     /// unregister an advertized service. If the service doesn't exists, this is no-op.
-    pub fn unregister(&mut self, svc: &str) {
-        self.advertized_sevices.remove(svc);
+    pub fn unregister<'a>(&mut self, svc: impl Into<Name<'a>>) {
+        self.advertized_sevices.remove(&svc.into().into_owned());
     }
 
     /// Adds a service to discover by the mdns server instance. When `ServiceDiscovery` is dropped, the service
     /// is not discovered anymore
-    pub fn discover(
+    pub fn discover<'a>(
         &mut self,
-        service_name: impl AsRef<str>,
+        service_name: impl Into<Name<'a>>,
         interval: Duration,
     ) -> ServiceDiscovery {
         let (otx, mut orx) = oneshot::channel();
         let mut interval = time::interval(interval);
         let sender = self.discovery_scheduler_snd.clone();
-        let service = service_name.as_ref().to_string();
+        let service_name = service_name.into().into_owned();
+        let service = service_name.clone();
         tokio::spawn(async move {
             loop {
                 let _ = interval.tick().await;
@@ -175,7 +684,7 @@ impl MdnsService {
                 }
             }
         });
-        ServiceDiscovery(otx, service_name.as_ref().to_string())
+        ServiceDiscovery(otx, service_name)
     }
 
     pub fn enqueue_response(&mut self, rsp: Vec<u8>) {
@@ -186,33 +695,42 @@ impl MdnsService {
         // Flush the query send buffer.
         while !self.send_buffers.is_empty() {
             let to_send = self.send_buffers.remove(0);
-            send_packets!(self, socket_v4, *IPV4_MDNS_MULTICAST_ADDRESS, to_send);
-            send_packets!(self, socket_v6, *IPV6_MDNS_MULTICAST_ADDRESS, to_send);
+            if let Some(socket) = &self.socket_v4 {
+                send_packets!(self, socket, self.multicast_addr_v4, to_send);
+            }
+            if let Some(socket) = &self.socket_v6 {
+                send_packets!(self, socket, self.multicast_addr_v6, to_send);
+            }
         }
 
         while !self.query_send_buffers.is_empty() {
             let to_send = self.query_send_buffers.remove(0);
-            send_packets!(
-                self,
-                query_socket,
-                &[*IPV4_MDNS_MULTICAST_ADDRESS, *IPV6_MDNS_MULTICAST_ADDRESS][..],
-                to_send
-            );
+            // the query socket is bound to an IPv4 address, unless IPv4 is disabled
+            let addr = match self.socket_v4 {
+                Some(_) => self.multicast_addr_v4,
+                None => self.multicast_addr_v6,
+            };
+            send_packets!(self, self.query_socket, addr, to_send);
         }
     }
 
     pub async fn next(&mut self) -> Packet {
         loop {
+            if let Some(event) = self.interface_events.pop_front() {
+                return Packet::Interface(event);
+            }
             self.send_buffers().await;
 
             tokio::select! {
-                Ok((len, from)) = self.socket_v4.recv_from(&mut self.recv_buffer_v4) => {
-                    if let Ok(packet) = self.parse_mdns_packets(&self.recv_buffer_v4[..len], from) {
+                Ok((len, from, info)) = recv_from(&self.socket_v4, &mut self.recv_buffer_v4) => {
+                    let buf = &self.recv_buffer_v4[..len];
+                    if let Ok(packet) = self.parse_mdns_packets(buf, from, info) {
                         return packet;
                     }
                 },
-                Ok((len, from)) = self.socket_v6.recv_from(&mut self.recv_buffer_v6) => {
-                    if let Ok(packet) = self.parse_mdns_packets(&self.recv_buffer_v6[..len], from) {
+                Ok((len, from, info)) = recv_from(&self.socket_v6, &mut self.recv_buffer_v6) => {
+                    let buf = &self.recv_buffer_v6[..len];
+                    if let Ok(packet) = self.parse_mdns_packets(buf, from, info) {
                         return packet;
                     }
                 },
@@ -224,38 +742,168 @@ impl MdnsService {
                         dns::QueryClass::IN,
                         dns::QueryType::PTR,
                     );
-                    let query = query.build();
-                    self.query_send_buffers.push(query);
+                    // the service name is provided by the user, and may not be a valid name
+                    if let Ok(query) = query.try_build() {
+                        self.query_send_buffers.push(query);
+                    }
+                }
+                events = watch(&mut self.watcher) => {
+                    match events {
+                        Ok(events) => self.interface_events.extend(events),
+                        // the interfaces are no longer followed
+                        Err(_) => self.watcher = None,
+                    }
+                    // interfaces may change again in the meantime, which is another event
+                    if let Ok(Some(interfaces)) = interface::list() {
+                        let _ = self.update_memberships(&interfaces);
+                    }
                 }
             }
         }
     }
 
-    fn parse_mdns_packets(&self, buf: &[u8], from: SocketAddr) -> Result<Packet, Error> {
-        let packet = dns_parser::Packet::parse(buf)?;
-        if packet.header.query {
-            let queries = packet
-                .questions
-                .iter()
-                .filter_map(|q| {
-                    let name = q.qname.to_string();
-                    if self.advertized_sevices.contains(&name) || name == META_QUERY_SERVICE {
-                        Some(Query {
-                            name,
-                            from,
-                            id: packet.header.id,
-                            qclass: q.qclass,
-                            qtype: q.qtype,
-                            prefer_unicast: q.prefer_unicast,
-                        })
-                    } else {
-                        None
-                    }
-                })
-                .collect::<Vec<_>>();
+    fn parse_mdns_packets(
+        &self,
+        buf: &[u8],
+        from: SocketAddr,
+        info: PacketInfo,
+    ) -> Result<Packet, Error> {
+        let message = dns::MessageRef::parse(buf)?;
+        if message.header().is_query() {
+            // questions borrow the receive buffer, and are only copied when they are for us
+            let mut queries = Vec::new();
+            for question in message.questions() {
+                let q = question?;
+                if self.advertized_sevices.contains(&q.name) || q.name == META_QUERY_SERVICE {
+                    queries.push(Query {
+                        name: q.name.into_owned(),
+                        from,
+                        info,
+                        id: message.header().id(),
+                        qclass: q.qclass,
+                        qtype: q.qtype,
+                        prefer_unicast: q.prefer_unicast,
+                    });
+                }
+            }
             Ok(Packet::Query(queries))
         } else {
-            Ok(Packet::Response(mdns::Response::from_packet(&packet)))
+            Ok(Packet::Response(Response {
+                message: message.into_owned()?,
+                from,
+                info,
+            }))
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns a builder for services on a port of their own, so that tests don't see each
+    /// other's traffic, nor the traffic of the mDNS responder of the machine. The port is a free
+    /// port picked by the system, so that tests of other binaries don't use it either.
+    fn isolated() -> MdnsServiceBuilder {
+        let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let mut builder = MdnsService::builder();
+        builder.set_port(port).set_ipv6(false).set_loopback(true);
+        builder
+    }
+
+    #[tokio::test]
+    async fn discover_on_custom_port() {
+        let builder = isolated();
+        let mut responder = builder.build().unwrap();
+        responder.register("_myservice._tcp.local");
+        let mut client = builder.build().unwrap();
+        let discovery = client.discover("_MyService._tcp.local.", Duration::from_millis(50));
+        assert_eq!(discovery.name(), "_myservice._tcp.local");
+
+        let client = tokio::spawn(async move {
+            loop {
+                client.next().await;
+            }
+        });
+        let queries = time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Packet::Query(queries) = responder.next().await {
+                    if !queries.is_empty() {
+                        return queries;
+                    }
+                }
+            }
+        })
+        .await
+        .unwrap();
+        client.abort();
+
+        assert_eq!(queries[0].name, "_myservice._tcp.local");
+        assert_eq!(queries[0].qtype, QueryType::PTR);
+        if cfg!(target_os = "linux") {
+            let info = queries[0].info;
+            assert_eq!(info.destination, Some(IPV4_MDNS_MULTICAST_GROUP.into()));
+            assert!(info.is_multicast());
+            assert_eq!(info.hop_limit, Some(255));
+            assert!(info.interface.is_some());
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn deny_all_interfaces() {
+        let service = isolated()
+            .deny_interface("*".parse().unwrap())
+            .build()
+            .unwrap();
+        assert!(service.interfaces().is_empty());
+    }
+
+    #[test]
+    fn reject_no_address_family() {
+        let error = MdnsService::builder()
+            .set_ipv4(false)
+            .set_ipv6(false)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::InvalidConfig(_)));
+    }
+
+    #[test]
+    fn reject_zero_channel_capacity() {
+        let error = MdnsService::builder()
+            .set_channel_capacity(0)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::InvalidConfig(_)));
+    }
+
+    #[test]
+    fn reject_zero_recv_buffer_size() {
+        let error = MdnsService::builder()
+            .set_recv_buffer_size(0)
+            .build()
+            .err()
+            .unwrap();
+        assert!(matches!(error, Error::InvalidConfig(_)));
+    }
+
+    #[test]
+    fn lookup_service_names() {
+        let mut names = HashSet::new();
+        names.insert(Name::from("_MyService._tcp.local").into_owned());
+        names.insert(Name::from("_other._udp.local").into_owned());
+
+        // names borrowing a received packet are looked up without being copied
+        let received = String::from("_myservice._TCP.local.");
+        assert!(names.contains(&Name::from(received.as_str())));
+        assert!(!names.contains(&Name::from("_myservice._udp.local")));
+
+        names.remove(&Name::from("_myservice._tcp.local"));
+        assert!(!names.contains(&Name::from("_MyService._tcp.local")));
+        assert!(names.contains(&Name::from("_other._udp.local")));
+    }
+}